@@ -0,0 +1,840 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+use zip::write::SimpleFileOptions;
+
+use crate::engine::{now_unix, reencode_zip_entry};
+use crate::error::{BlkError, BlkResult, Context, Report};
+use crate::events::{CancelToken, EngineEvent, EventSender};
+use crate::lock::RepoLock;
+use crate::meta::{load_manifest, resolve_chain, save_manifest, set_ids, validate_set_ids};
+use crate::models::{BlkConfig, BundleManifest, SetManifest};
+use crate::storage::{open_store, SnapshotStore};
+
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+pub const BUNDLE_EXTENSION: &str = "blkb";
+
+// -----------------------------------------------------------------------------
+// Helpers
+// -----------------------------------------------------------------------------
+
+// Snapshots antigos (vanilla) não têm prefixo de scope: caem na raiz
+fn normalize_entry_name(name: &str, scopes: &HashSet<String>) -> String {
+    match name.split_once('/') {
+        Some((first, _)) if scopes.contains(first) => name.to_string(),
+        _ if scopes.contains(name) => name.to_string(),
+        _ => format!("Root/{name}"),
+    }
+}
+
+/// Grava um zip como membro (sem compressão) e devolve o sha256 do conteúdo.
+fn add_member<W: Write + io::Seek>(
+    bundle: &mut zip::ZipWriter<W>,
+    name: &str,
+    mut f: impl Read,
+    len: u64,
+) -> BlkResult<String> {
+    let options = SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Stored)
+        .large_file(len > 0xffffffff);
+    bundle.start_file(name, options).at("add bundle member", name)?;
+
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1024 * 1024];
+    loop {
+        let n = f.read(&mut buf).at("read", name)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        bundle.write_all(&buf[..n]).at("write bundle member", name)?;
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Reconstrói a cadeia numa camada única: cada caminho vem da camada mais alta
+/// que o fornece, depois de aplicar os `deleted_paths` em ordem.
+fn flatten_chain(
+    store: &dyn SnapshotStore,
+    chain: &[SetManifest],
+    scopes: &HashSet<String>,
+    out: &Path,
+    config: &BlkConfig,
+    cancel: &CancelToken,
+) -> BlkResult<usize> {
+    let mut winners: HashMap<String, (usize, usize)> = HashMap::new();
+
+    for (layer, man) in chain.iter().enumerate() {
+        if !store.contains(&man.id) {
+            return Err(BlkError::NotFound(format!("{}: snapshot layer is missing", man.id)));
+        }
+        let file = store.open(&man.id).at("open snapshot", &man.id)?;
+        let mut archive = zip::ZipArchive::new(file).at("read snapshot", &man.id)?;
+
+        for i in 0..archive.len() {
+            let entry = archive.by_index_raw(i).at("read snapshot", &man.id)?;
+            if entry.enclosed_name().is_none() {
+                continue;
+            }
+            let key = normalize_entry_name(entry.name().trim_end_matches('/'), scopes);
+            // A pasta do próprio scope não é uma entrada rastreada
+            if !key.contains('/') {
+                continue;
+            }
+            winners.insert(key, (layer, i));
+        }
+
+        for del_key in &man.deleted_paths {
+            if let Some((scope, rel)) = del_key.split_once("::") {
+                winners.remove(&format!("{scope}/{rel}"));
+            }
+        }
+    }
+
+    let mut layers: Vec<Vec<(usize, String)>> = vec![Vec::new(); chain.len()];
+    for (key, (layer, index)) in winners {
+        layers[layer].push((index, key));
+    }
+
+    if let Some(parent) = out.parent() {
+        fs::create_dir_all(parent).at("create directory", parent)?;
+    }
+    let file = File::create(out).at("create", out)?;
+    let mut zip = zip::ZipWriter::new(file);
+    let mut count = 0;
+
+    for (layer, entries) in layers.iter_mut().enumerate() {
+        if entries.is_empty() {
+            continue;
+        }
+        entries.sort_by_key(|(index, _)| *index);
+
+        let id = &chain[layer].id;
+        let file = store.open(id).at("open snapshot", id)?;
+        let mut archive = zip::ZipArchive::new(file).at("read snapshot", id)?;
+
+        for (index, key) in entries.iter() {
+            cancel.check()?;
+            let mut entry = archive.by_index(*index).at("read snapshot", id)?;
+            reencode_zip_entry(&mut entry, &mut zip, key, &config.compression)?;
+            count += 1;
+        }
+    }
+
+    zip.finish().at("finalize", out)?;
+    Ok(count)
+}
+
+// -----------------------------------------------------------------------------
+// Export
+// -----------------------------------------------------------------------------
+
+pub fn default_export_path(app_root: &Path, id: &str, flatten: bool) -> PathBuf {
+    let suffix = if flatten { "-flat" } else { "" };
+    app_root
+        .join(".blk")
+        .join("exports")
+        .join(format!("{id}{suffix}.{BUNDLE_EXTENSION}"))
+}
+
+fn export_bundle(
+    app_root: &Path,
+    config: &BlkConfig,
+    target_id: &str,
+    dest: &Path,
+    flatten: bool,
+    cancel: &CancelToken,
+    tx: &EventSender,
+) -> BlkResult<usize> {
+    let chain = resolve_chain(app_root, target_id)?;
+    match chain.last() {
+        Some(m) if m.id == target_id => {}
+        _ => return Err(BlkError::NotFound(format!("Set '{target_id}' not found"))),
+    }
+    if let Some(parent) = chain.first().and_then(|m| m.parent_id.clone()) {
+        return Err(BlkError::NotFound(format!(
+            "Broken lineage: parent '{parent}' is missing"
+        )));
+    }
+
+    let store = open_store(app_root, config)?;
+
+    let used_scopes: HashSet<String> = chain
+        .iter()
+        .flat_map(|m| m.scopes.iter().cloned())
+        .collect();
+    let mut scopes: Vec<String> = used_scopes.iter().cloned().collect();
+    scopes.sort();
+
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).at("create directory", parent)?;
+    }
+    // Escreve ao lado e renomeia no fim: nunca fica um pacote pela metade
+    let partial = dest.with_extension(format!("{BUNDLE_EXTENSION}.part"));
+    let file = File::create(&partial).at("create", &partial)?;
+    let mut bundle = zip::ZipWriter::new(file);
+    let mut checksums = HashMap::new();
+
+    let sets = if flatten {
+        tx.send(EngineEvent::phase(10.0, format!("Flattening {} layers...", chain.len()))).ok();
+        let mut norm_scopes = used_scopes.clone();
+        norm_scopes.insert("Root".into());
+
+        let flat_path = app_root
+            .join(".blk")
+            .join("tmp")
+            .join(format!("{target_id}.flat.zip"));
+        let result = flatten_chain(store.as_ref(), &chain, &norm_scopes, &flat_path, config, cancel)
+            .and_then(|_| {
+                tx.send(EngineEvent::phase(60.0, "Packing flattened layer...")).ok();
+                let member = format!("layers/{target_id}.zip");
+                let file = File::open(&flat_path).at("open", &flat_path)?;
+                let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+                let sum = add_member(&mut bundle, &member, file, len)?;
+                checksums.insert(member, sum);
+                Ok(())
+            });
+        let _ = fs::remove_file(&flat_path);
+        result?;
+
+        // Uma camada só: sem pai e sem deleções para aplicar
+        let target = chain[chain.len() - 1].clone();
+        vec![SetManifest {
+            parent_id: None,
+            deleted_paths: vec![],
+            ..target
+        }]
+    } else {
+        let total = chain.len().max(1) as f32;
+        for (i, man) in chain.iter().enumerate() {
+            cancel.check()?;
+            let pct = 10.0 + (i as f32 / total) * 80.0;
+            tx.send(EngineEvent::phase(pct, format!("Packing layer {}/{} ({})", i + 1, chain.len(), man.id)))
+                .ok();
+            // Sem a camada o pacote sairia com um buraco no meio da cadeia
+            let len = store.size(&man.id).ok_or_else(|| {
+                BlkError::NotFound(format!("{}: snapshot layer is missing", man.id))
+            })?;
+            let member = format!("layers/{}.zip", man.id);
+            let src = store.open(&man.id).at("open snapshot", &man.id)?;
+            let sum = add_member(&mut bundle, &member, src, len)?;
+            checksums.insert(member, sum);
+        }
+        chain
+    };
+
+    let layer_count = sets.len();
+    let manifest = BundleManifest {
+        format_version: BUNDLE_FORMAT_VERSION,
+        created_at: now_unix(),
+        target_id: target_id.to_string(),
+        flattened: flatten,
+        scopes,
+        sets,
+        checksums,
+    };
+    let json = serde_json::to_string_pretty(&manifest).at("encode", "bundle.json")?;
+    bundle
+        .start_file(
+            "bundle.json",
+            SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated),
+        )
+        .at("add bundle member", "bundle.json")?;
+    bundle.write_all(json.as_bytes()).at("write bundle member", "bundle.json")?;
+    bundle.finish().at("finalize", &partial)?;
+
+    fs::rename(&partial, dest).at("move bundle into place at", dest)?;
+    Ok(layer_count)
+}
+
+pub fn engine_export_bundle(
+    app_root: &Path,
+    config: BlkConfig,
+    target_id: String,
+    dest: PathBuf,
+    flatten: bool,
+    cancel: CancelToken,
+    tx: EventSender,
+) -> BlkResult<Report> {
+    tx.send(EngineEvent::Started { operation: "export" }).ok();
+    tx.send(EngineEvent::phase(0.0, format!("Exporting '{target_id}'..."))).ok();
+
+    match export_bundle(app_root, &config, &target_id, &dest, flatten, &cancel, &tx) {
+        Ok(layers) => Ok(Report::new(format!(
+            "Exported {} layer(s) to {}",
+            layers,
+            dest.to_string_lossy()
+        ))),
+        Err(e) => {
+            let _ = fs::remove_file(dest.with_extension(format!("{BUNDLE_EXTENSION}.part")));
+            Err(e)
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Import
+// -----------------------------------------------------------------------------
+
+fn read_bundle_json<R: Read + io::Seek>(
+    bundle: &mut zip::ZipArchive<R>,
+) -> BlkResult<BundleManifest> {
+    let mut txt = String::new();
+    bundle
+        .by_name("bundle.json")
+        .map_err(|_| BlkError::Integrity("bundle.json is missing".into()))?
+        .read_to_string(&mut txt)
+        .at("read", "bundle.json")?;
+
+    let manifest: BundleManifest = serde_json::from_str(&txt).at("parse", "bundle.json")?;
+    if manifest.format_version > BUNDLE_FORMAT_VERSION {
+        return Err(BlkError::Integrity(format!(
+            "Bundle format v{} is newer than this build supports (v{})",
+            manifest.format_version, BUNDLE_FORMAT_VERSION
+        )));
+    }
+    // Os ids viram nomes de arquivo no import: valida antes de qualquer I/O
+    for man in &manifest.sets {
+        validate_set_ids("bundle.json", man)?;
+    }
+    Ok(manifest)
+}
+
+/// Lê só o bundle.json, para a TUI perguntar o mapeamento de cada scope.
+pub fn read_bundle_manifest(path: &Path) -> BlkResult<BundleManifest> {
+    let file = File::open(path).at("open", path)?;
+    let mut bundle = zip::ZipArchive::new(file).at("read bundle", path)?;
+    read_bundle_json(&mut bundle)
+}
+
+/// Todos os scopes que as camadas do bundle usam (vanilla sem prefixo = Root).
+pub fn bundle_scopes(manifest: &BundleManifest) -> Vec<String> {
+    let mut scopes: Vec<String> = manifest
+        .scopes
+        .iter()
+        .chain(manifest.sets.iter().flat_map(|m| m.scopes.iter()))
+        .cloned()
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    if scopes.is_empty() {
+        scopes.push("Root".into());
+    }
+    scopes.sort();
+    scopes
+}
+
+/// Copia um membro do bundle para `out`, conferindo o sha256 gravado no export.
+fn extract_member<R: Read + io::Seek, W: Write>(
+    bundle: &mut zip::ZipArchive<R>,
+    manifest: &BundleManifest,
+    member: &str,
+    out: &mut W,
+) -> BlkResult<()> {
+    let expected = manifest
+        .checksums
+        .get(member)
+        .ok_or_else(|| BlkError::Integrity(format!("No checksum recorded for {member}")))?;
+    let mut src = bundle.by_name(member).at("find bundle member", member)?;
+
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1024 * 1024];
+    loop {
+        let n = src.read(&mut buf).at("read", member)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        out.write_all(&buf[..n]).at("extract", member)?;
+    }
+
+    if hex::encode(hasher.finalize()) != *expected {
+        return Err(BlkError::Integrity(format!(
+            "Checksum mismatch for {member}: bundle is corrupted"
+        )));
+    }
+    Ok(())
+}
+
+fn map_scope(scope: &str, scope_map: &HashMap<String, String>) -> String {
+    scope_map.get(scope).cloned().unwrap_or_else(|| scope.to_string())
+}
+
+/// Reescreve um manifesto do bundle com os scopes locais.
+fn remap_manifest(man: &SetManifest, scope_map: &HashMap<String, String>) -> SetManifest {
+    let mut scopes: Vec<String> = Vec::new();
+    for scope in &man.scopes {
+        let local = map_scope(scope, scope_map);
+        if !scopes.contains(&local) {
+            scopes.push(local);
+        }
+    }
+    let deleted_paths = man
+        .deleted_paths
+        .iter()
+        .map(|key| match key.split_once("::") {
+            Some((scope, rel)) => format!("{}::{rel}", map_scope(scope, scope_map)),
+            None => key.clone(),
+        })
+        .collect();
+
+    SetManifest {
+        scopes,
+        deleted_paths,
+        ..man.clone()
+    }
+}
+
+/// Regrava uma camada trocando o prefixo de scope de cada entrada.
+fn remap_layer<W: Write + io::Seek>(
+    src: &Path,
+    out: W,
+    bundle_scopes: &HashSet<String>,
+    scope_map: &HashMap<String, String>,
+    config: &BlkConfig,
+) -> BlkResult<W> {
+    let file = File::open(src).at("open", src)?;
+    let mut archive = zip::ZipArchive::new(file).at("read", src)?;
+    let mut zip = zip::ZipWriter::new(out);
+
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).at("read", src)?;
+        if entry.enclosed_name().is_none() {
+            continue;
+        }
+        let is_dir = entry.is_dir();
+        let key = normalize_entry_name(entry.name().trim_end_matches('/'), bundle_scopes);
+        let (scope, rel) = key.split_once('/').unwrap_or((key.as_str(), ""));
+        let mut name = map_scope(scope, scope_map);
+        if !rel.is_empty() {
+            name = format!("{name}/{rel}");
+        }
+        if is_dir {
+            name.push('/');
+        }
+        reencode_zip_entry(&mut entry, &mut zip, &name, &config.compression)?;
+    }
+
+    zip.finish().at("remap", src)
+}
+
+pub(crate) fn unique_set_id(
+    app_root: &Path,
+    store: &dyn SnapshotStore,
+    id: &str,
+    taken: &HashSet<String>,
+) -> String {
+    let free = |candidate: &str| {
+        !taken.contains(candidate)
+            && matches!(load_manifest(app_root, candidate), Ok(None))
+            && !store.contains(candidate)
+    };
+    let base = format!("{id}-imported");
+    if free(&base) {
+        return base;
+    }
+    (2..)
+        .map(|n| format!("{base}-{n}"))
+        .find(|candidate| free(candidate))
+        .unwrap()
+}
+
+/// Um import anterior do mesmo set já foi renomeado para `{id}-imported*`.
+pub(crate) fn previous_import(app_root: &Path, local: &SetManifest) -> Option<String> {
+    let prefix = format!("{}-imported", local.id);
+    set_ids(app_root)
+        .into_iter()
+        .filter(|id| id.starts_with(&prefix))
+        .find(|id| {
+            let expected = SetManifest {
+                id: id.clone(),
+                ..local.clone()
+            };
+            matches!(load_manifest(app_root, id), Ok(Some(m)) if m == expected)
+        })
+}
+
+struct ImportReport {
+    imported: Vec<String>,
+    skipped: Vec<String>,
+    renamed: Vec<(String, String)>,
+}
+
+struct PlannedSet {
+    manifest: SetManifest,
+    member: String,
+    staged: PathBuf,
+    skip: bool,
+}
+
+fn import_bundle(
+    app_root: &Path,
+    config: &BlkConfig,
+    bundle_path: &Path,
+    scope_map: &HashMap<String, String>,
+    cancel: &CancelToken,
+    tx: &EventSender,
+) -> BlkResult<ImportReport> {
+    let file = File::open(bundle_path).at("open", bundle_path)?;
+    let mut bundle = zip::ZipArchive::new(file).at("read bundle", bundle_path)?;
+    let manifest = read_bundle_json(&mut bundle)?;
+    let store = open_store(app_root, config)?;
+
+    // Todo scope do bundle precisa cair num scope local que exista no path_map
+    let scopes = bundle_scopes(&manifest);
+    let mut targets: HashMap<String, String> = HashMap::new();
+    for scope in &scopes {
+        let local = map_scope(scope, scope_map);
+        if !config.path_map.contains_key(&local) {
+            return Err(BlkError::Config(format!(
+                "Scope '{scope}' maps to '{local}', which is not in the path map"
+            )));
+        }
+        if let Some(other) = targets.insert(local.clone(), scope.clone()) {
+            return Err(BlkError::Config(format!(
+                "Scopes '{other}' and '{scope}' both map to '{local}'"
+            )));
+        }
+    }
+    let identity = scopes.iter().all(|s| map_scope(s, scope_map) == *s);
+
+    let mut report = ImportReport {
+        imported: vec![],
+        skipped: vec![],
+        renamed: vec![],
+    };
+
+    // Resolve IDs antes de tocar no disco: pais do bundle seguem os renomeados
+    let bundle_ids: HashSet<String> = manifest.sets.iter().map(|m| m.id.clone()).collect();
+    let mut id_map: HashMap<String, String> = HashMap::new();
+    let mut taken: HashSet<String> = HashSet::new();
+    let mut planned: Vec<PlannedSet> = Vec::new();
+    let tmp_dir = app_root.join(".blk").join("tmp");
+
+    for man in &manifest.sets {
+        let mut local = remap_manifest(man, scope_map);
+
+        // Soltar um delta do pai o transformaria em raiz: sem a cadeia inteira,
+        // o import não acontece
+        local.parent_id = match &man.parent_id {
+            Some(p) if bundle_ids.contains(p) => match id_map.get(p) {
+                Some(id) => Some(id.clone()),
+                None => {
+                    return Err(BlkError::Integrity(format!(
+                        "Bundle lists set '{}' before its parent '{p}'",
+                        man.id
+                    )))
+                }
+            },
+            Some(p) if load_manifest(app_root, p)?.is_some() => Some(p.clone()),
+            Some(p) => {
+                return Err(BlkError::NotFound(format!(
+                    "Set '{}' needs parent '{p}', which is neither in the bundle nor in this repository; export the full chain",
+                    man.id
+                )))
+            }
+            None => None,
+        };
+
+        let skip = match load_manifest(app_root, &man.id)? {
+            Some(existing) if existing == local => true,
+            Some(_) => match previous_import(app_root, &local) {
+                Some(id) => {
+                    local.id = id;
+                    true
+                }
+                None => {
+                    local.id = unique_set_id(app_root, store.as_ref(), &man.id, &taken);
+                    report.renamed.push((man.id.clone(), local.id.clone()));
+                    false
+                }
+            },
+            None => false,
+        };
+
+        id_map.insert(man.id.clone(), local.id.clone());
+        taken.insert(local.id.clone());
+        planned.push(PlannedSet {
+            staged: tmp_dir.join(format!("{}.import.zip", local.id)),
+            member: format!("layers/{}.zip", man.id),
+            manifest: local,
+            skip,
+        });
+    }
+
+    // Fase 1: extrai e confere todas as camadas; nada entra no repositório ainda,
+    // então é aqui (e só aqui) que dá para cancelar
+    fs::create_dir_all(&tmp_dir).at("create directory", &tmp_dir)?;
+    let total = planned.len().max(1) as f32;
+    for (i, set) in planned.iter().enumerate() {
+        cancel.check()?;
+        // Os filhos apontam para este set: pular a camada quebraria a cadeia
+        if bundle.by_name(&set.member).is_err() {
+            return Err(BlkError::Integrity(format!(
+                "Bundle is missing {} for set '{}'",
+                set.member, set.manifest.id
+            )));
+        }
+        let pct = 5.0 + (i as f32 / total) * 45.0;
+        tx.send(EngineEvent::phase(pct, format!("Verifying layer {}/{} ({})", i + 1, planned.len(), set.member)))
+            .ok();
+        if set.skip {
+            extract_member(&mut bundle, &manifest, &set.member, &mut io::sink())?;
+        } else {
+            let mut out = File::create(&set.staged).at("create", &set.staged)?;
+            extract_member(&mut bundle, &manifest, &set.member, &mut out)?;
+        }
+    }
+
+    // Fase 2: camadas primeiro, manifesto por último
+    let mut norm_scopes: HashSet<String> = scopes.iter().cloned().collect();
+    norm_scopes.insert("Root".into());
+    for (i, set) in planned.iter().enumerate() {
+        let id = &set.manifest.id;
+        if set.skip {
+            report.skipped.push(id.clone());
+            continue;
+        }
+        let pct = 50.0 + (i as f32 / total) * 50.0;
+        tx.send(EngineEvent::phase(pct, format!("Importing layer {}/{} ({id})", i + 1, planned.len())))
+            .ok();
+
+        if identity {
+            store.put_file(id, &set.staged).at("store snapshot", id)?;
+        } else {
+            let writer = store.create(id).at("create snapshot", id)?;
+            remap_layer(&set.staged, writer, &norm_scopes, scope_map, config)?
+                .commit()
+                .at("store snapshot", id)?;
+            let _ = fs::remove_file(&set.staged);
+        }
+
+        save_manifest(app_root, &set.manifest)?;
+        report.imported.push(id.clone());
+    }
+
+    Ok(report)
+}
+
+pub fn engine_import_bundle(
+    app_root: &Path,
+    config: BlkConfig,
+    bundle_path: PathBuf,
+    scope_map: HashMap<String, String>,
+    cancel: CancelToken,
+    tx: EventSender,
+) -> BlkResult<Report> {
+    tx.send(EngineEvent::Started { operation: "import" }).ok();
+    tx.send(EngineEvent::phase(0.0, format!("Importing {}...", bundle_path.to_string_lossy())))
+        .ok();
+    let _lock = RepoLock::acquire(app_root, "import")?;
+
+    let result = import_bundle(app_root, &config, &bundle_path, &scope_map, &cancel, &tx);
+
+    // Camadas em staging que sobraram (erro no meio ou sets pulados)
+    if let Ok(entries) = fs::read_dir(app_root.join(".blk").join("tmp")) {
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().ends_with(".import.zip") {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    let report = result?;
+    let mut msg = format!("Imported {} set(s)", report.imported.len());
+    if !report.skipped.is_empty() {
+        msg.push_str(&format!(", {} already present", report.skipped.len()));
+    }
+    for (from, to) in &report.renamed {
+        msg.push_str(&format!(" | {from} → {to}"));
+    }
+    Ok(Report::new(msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{engine_auto_init, engine_set_contents, engine_update_global_path};
+    use crate::meta::load_config;
+    use crate::test_support::{init_repo, set_manifest, silent};
+
+    fn empty_zip() -> Vec<u8> {
+        zip::ZipWriter::new(io::Cursor::new(Vec::new()))
+            .finish()
+            .unwrap()
+            .into_inner()
+    }
+
+    // Pacote montado à mão; os checksums dos membros são calculados aqui
+    fn write_bundle(path: &Path, sets: Vec<SetManifest>, members: &[(&str, Vec<u8>)]) {
+        let mut manifest = BundleManifest {
+            format_version: BUNDLE_FORMAT_VERSION,
+            created_at: now_unix(),
+            target_id: sets.last().map(|m| m.id.clone()).unwrap_or_default(),
+            flattened: false,
+            scopes: vec!["Root".into()],
+            sets,
+            checksums: HashMap::new(),
+        };
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, data) in members {
+            let sum = add_member(&mut zip, name, data.as_slice(), data.len() as u64).unwrap();
+            manifest.checksums.insert(name.to_string(), sum);
+        }
+        zip.start_file("bundle.json", SimpleFileOptions::default()).unwrap();
+        zip.write_all(serde_json::to_string(&manifest).unwrap().as_bytes())
+            .unwrap();
+        zip.finish().unwrap();
+    }
+
+    fn import(root: &Path, bundle: &Path) -> BlkResult<Report> {
+        import_mapped(root, bundle, HashMap::new())
+    }
+
+    fn import_mapped(root: &Path, bundle: &Path, scope_map: HashMap<String, String>) -> BlkResult<Report> {
+        engine_import_bundle(
+            root,
+            load_config(root).unwrap(),
+            bundle.to_path_buf(),
+            scope_map,
+            CancelToken::default(),
+            silent(),
+        )
+    }
+
+    #[test]
+    fn export_fails_when_a_layer_is_missing() {
+        let repo = init_repo(&[("a.txt", "a")]);
+        fs::remove_file(repo.path().join(".blk/snapshots/vanilla.zip")).unwrap();
+
+        for flatten in [false, true] {
+            let dest = repo.path().join("out.blkb");
+            let err = engine_export_bundle(
+                repo.path(),
+                load_config(repo.path()).unwrap(),
+                "vanilla".into(),
+                dest.clone(),
+                flatten,
+                CancelToken::default(),
+                silent(),
+            )
+            .unwrap_err();
+            assert!(matches!(err, BlkError::NotFound(_)), "{err}");
+            assert!(!dest.exists());
+        }
+    }
+
+    #[test]
+    fn import_refuses_a_bundle_with_a_missing_layer() {
+        let repo = init_repo(&[]);
+        let bundle = repo.path().join("gap.blkb");
+        write_bundle(
+            &bundle,
+            vec![set_manifest("base", None), set_manifest("child", Some("base"))],
+            &[("layers/child.zip", empty_zip())],
+        );
+
+        let err = import(repo.path(), &bundle).unwrap_err();
+
+        assert!(matches!(err, BlkError::Integrity(_)), "{err}");
+        assert_eq!(load_manifest(repo.path(), "base").unwrap(), None);
+        assert_eq!(load_manifest(repo.path(), "child").unwrap(), None);
+    }
+
+    #[test]
+    fn import_refuses_a_delta_whose_parent_is_nowhere() {
+        let repo = init_repo(&[]);
+        let bundle = repo.path().join("orphan.blkb");
+        write_bundle(
+            &bundle,
+            vec![set_manifest("child", Some("base"))],
+            &[("layers/child.zip", empty_zip())],
+        );
+
+        let err = import(repo.path(), &bundle).unwrap_err();
+
+        assert!(matches!(err, BlkError::NotFound(_)), "{err}");
+        assert_eq!(set_ids(repo.path()), ["vanilla"]);
+        assert!(!repo.path().join(".blk/snapshots/child.zip").exists());
+
+        // Com o pai já no repositório o mesmo bundle entra
+        save_manifest(repo.path(), &set_manifest("base", None)).unwrap();
+        import(repo.path(), &bundle).unwrap();
+        let child = load_manifest(repo.path(), "child").unwrap().unwrap();
+        assert_eq!(child.parent_id.as_deref(), Some("base"));
+    }
+
+    #[test]
+    fn import_rejects_set_ids_that_leave_the_repo() {
+        // O repo fica um nível abaixo para um escape cair dentro do tempdir
+        let outer = tempfile::tempdir().unwrap();
+        let root = outer.path().join("repo");
+        fs::create_dir(&root).unwrap();
+        engine_auto_init(&root, silent()).unwrap();
+
+        let evil = "../../../escape";
+        for sets in [
+            vec![set_manifest(evil, None)],
+            vec![set_manifest("base", None), set_manifest("child", Some(evil))],
+        ] {
+            let bundle = outer.path().join("evil.blkb");
+            let members: Vec<(String, Vec<u8>)> = sets
+                .iter()
+                .map(|m| (format!("layers/{}.zip", m.id), empty_zip()))
+                .collect();
+            let members: Vec<(&str, Vec<u8>)> =
+                members.iter().map(|(n, d)| (n.as_str(), d.clone())).collect();
+            write_bundle(&bundle, sets, &members);
+
+            let err = import(&root, &bundle).unwrap_err();
+
+            assert!(matches!(err, BlkError::Integrity(_)), "{err}");
+            let mut names: Vec<_> = fs::read_dir(outer.path())
+                .unwrap()
+                .map(|e| e.unwrap().file_name())
+                .collect();
+            names.sort();
+            assert_eq!(names, ["evil.blkb", "repo"]);
+            assert_eq!(set_ids(&root), ["vanilla"]);
+        }
+    }
+
+    #[test]
+    fn import_moves_layers_to_the_mapped_scope_and_renames_conflicts_once() {
+        let theirs = init_repo(&[("save/slot1.dat", "theirs")]);
+        let out = tempfile::tempdir().unwrap();
+        let bundle = out.path().join("vanilla.blkb");
+        engine_export_bundle(
+            theirs.path(),
+            load_config(theirs.path()).unwrap(),
+            "vanilla".into(),
+            bundle.clone(),
+            false,
+            CancelToken::default(),
+            silent(),
+        )
+        .unwrap();
+        let mine = init_repo(&[("a.txt", "mine")]);
+        engine_update_global_path(mine.path(), "Game".into(), "${ROOT}/game".into()).unwrap();
+        let map = HashMap::from([("Root".to_string(), "Game".to_string())]);
+
+        let first = import_mapped(mine.path(), &bundle, map.clone()).unwrap();
+        let second = import_mapped(mine.path(), &bundle, map).unwrap();
+
+        assert_eq!(first.summary, "Imported 1 set(s) | vanilla → vanilla-imported");
+        assert_eq!(second.summary, "Imported 0 set(s), 1 already present");
+        let imported = load_manifest(mine.path(), "vanilla-imported").unwrap().unwrap();
+        assert_eq!(imported.scopes, ["Game"]);
+        let config = load_config(mine.path()).unwrap();
+        let paths: Vec<String> = engine_set_contents(mine.path(), &config, &["vanilla-imported".into()])
+            .unwrap()
+            .into_iter()
+            .map(|e| e.path)
+            .collect();
+        assert_eq!(paths, ["Game/save", "Game/save/slot1.dat"]);
+    }
+}
@@ -1,45 +1,261 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::{self, File};
 use std::io;
 use std::path::{Path, PathBuf};
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use chrono::{Datelike, Local, Timelike};
+use chrono::{Datelike, Local, TimeZone, Timelike};
 use glob::Pattern;
 use sha2::{Digest, Sha256};
 use walkdir::WalkDir;
-use zip::write::FileOptions;
+use zip::write::FullFileOptions;
 use zip::DateTime as ZipDateTime;
 
-use crate::models::{BlkConfig, DiffSummary, FileEntry, SetManifest};
+use crate::models::{
+    BlkConfig, Codec, CompressionConfig, CompressionProfile, ContentEntry, DiffSummary, EntryKind,
+    FileEntry, ScopeStatus, SetManifest,
+};
+use crate::error::{BlkError, BlkResult, Context, Report};
+use crate::events::{CancelToken, EngineEvent, EventSender, FileCounter};
+use crate::lock::RepoLock;
+use crate::meta::{
+    load_baseline, load_manifest, load_manifests, manifest_path, save_baseline, save_config, save_manifest,
+    sets_dir, update_config, validate_set_ids,
+};
+use crate::migrate::{stamp_format, FORMAT_VERSION};
+use crate::paths::{expand_path, resolve_path_map, ROOT_VAR};
+use crate::storage::{open_store, ReadSeek, SnapshotStore};
 
 // -----------------------------------------------------------------------------
 // Time helpers
 // -----------------------------------------------------------------------------
 
-fn now_unix() -> u64 {
+pub(crate) fn now_unix() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_secs()
 }
 
+fn mtime_secs(meta: &fs::Metadata) -> u64 {
+    meta.modified()
+        .unwrap_or(UNIX_EPOCH)
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+// -----------------------------------------------------------------------------
+// Metadata helpers (permissões, symlinks, mtime)
+// -----------------------------------------------------------------------------
+
+#[cfg(unix)]
+fn entry_mode(meta: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    meta.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn entry_mode(meta: &fs::Metadata) -> u32 {
+    if meta.is_dir() {
+        0o755
+    } else if meta.permissions().readonly() {
+        0o444
+    } else {
+        0o644
+    }
+}
+
+#[cfg(unix)]
+fn apply_mode(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o7777))
+}
+
+#[cfg(not(unix))]
+fn apply_mode(path: &Path, mode: u32) -> io::Result<()> {
+    let mut perms = fs::metadata(path)?.permissions();
+    perms.set_readonly(mode & 0o222 == 0);
+    fs::set_permissions(path, perms)
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn create_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    let resolved = link.parent().map(|p| p.join(target)).unwrap_or_default();
+    if resolved.is_dir() {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
+
+#[cfg(unix)]
+fn set_file_mtime(path: &Path, secs: u64) -> io::Result<()> {
+    File::open(path)?.set_modified(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+#[cfg(not(unix))]
+fn set_file_mtime(path: &Path, secs: u64) -> io::Result<()> {
+    fs::OpenOptions::new()
+        .write(true)
+        .open(path)?
+        .set_modified(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+// Remove o que estiver no destino quando o tipo não bate (arquivo vs pasta vs link)
+fn clear_destination(dest: &Path, want_dir: bool) -> io::Result<()> {
+    match fs::symlink_metadata(dest) {
+        Ok(meta) if meta.is_dir() => {
+            if want_dir {
+                Ok(())
+            } else {
+                fs::remove_dir_all(dest)
+            }
+        }
+        Ok(_) => fs::remove_file(dest),
+        Err(_) => Ok(()),
+    }
+}
+
 // -----------------------------------------------------------------------------
 // Public helpers
 // -----------------------------------------------------------------------------
 
-pub fn get_snapshot_size(app_root: &Path, id: &str) -> u64 {
-    let path = app_root
-        .join(".blk")
-        .join("snapshots")
-        .join(format!("{id}.zip"));
-    if let Ok(meta) = fs::metadata(path) {
-        meta.len()
+pub fn get_snapshot_size(app_root: &Path, config: &BlkConfig, id: &str) -> u64 {
+    open_store(app_root, config)
+        .ok()
+        .and_then(|store| store.size(id))
+        .unwrap_or(0)
+}
+
+/// Expande o caminho de `key` e mede o que há lá (mesmos filtros do save).
+/// Pode demorar num scope grande: chamar fora da thread da interface.
+pub fn engine_scope_status(app_root: &Path, key: &str, raw: &Path) -> ScopeStatus {
+    let resolved = expand_path(raw, app_root).map_err(|e| e.to_string());
+    let mut status = ScopeStatus {
+        key: key.to_string(),
+        raw: raw.to_path_buf(),
+        resolved,
+        exists: false,
+        is_dir: false,
+        files: 0,
+        bytes: 0,
+    };
+    let path = match &status.resolved {
+        Ok(path) => path.clone(),
+        Err(_) => return status,
+    };
+    match fs::metadata(&path) {
+        Ok(meta) => {
+            status.exists = true;
+            status.is_dir = meta.is_dir();
+        }
+        Err(_) => return status,
+    }
+
+    for entry in WalkDir::new(&path).into_iter().flatten() {
+        if entry.file_type().is_dir() || should_ignore(entry.path()) {
+            continue;
+        }
+        status.files += 1;
+        status.bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
+    }
+    status
+}
+
+/// Nome da entrada com o scope na frente, como `resolve_destination` o
+/// entende: camadas do init gravam sem o "Root/". None se o restore a ignora.
+fn scoped_name(config: &BlkConfig, name: &str) -> Option<String> {
+    let (scope, rest) = name.split_once('/').unwrap_or((name, ""));
+    if config.path_map.contains_key(scope) {
+        (!rest.is_empty()).then(|| name.to_string())
+    } else if config.path_map.contains_key("Root") {
+        Some(format!("Root/{name}"))
     } else {
-        0
+        None
+    }
+}
+
+/// Conteúdo da cadeia `ids` (da raiz ao set) como o restore a deixaria: cada
+/// caminho vem da camada mais alta que o fornece. O que um `deleted_paths`
+/// apagou (e nenhuma camada de cima recriou) volta com `deleted`. Com um id
+/// só, é a camada do set mais o que ela apaga. Só lê o diretório central dos
+/// zips; nada é extraído.
+pub fn engine_set_contents(
+    app_root: &Path,
+    config: &BlkConfig,
+    ids: &[String],
+) -> BlkResult<Vec<ContentEntry>> {
+    let store = open_store(app_root, config)?;
+    let mut entries: HashMap<String, ContentEntry> = HashMap::new();
+
+    for id in ids {
+        if store.contains(id) {
+            let file = store.open(id).at("open snapshot", id)?;
+            let mut archive = zip::ZipArchive::new(file).at("read snapshot", id)?;
+
+            for i in 0..archive.len() {
+                let entry = archive.by_index_raw(i).at("read snapshot", id)?;
+                // Mesmo filtro do restore: nomes que escapariam do scope não contam
+                if entry.enclosed_name().is_none() {
+                    continue;
+                }
+                let path = match scoped_name(config, entry.name().trim_end_matches('/')) {
+                    Some(p) => p,
+                    None => continue,
+                };
+                let kind = if entry.is_dir() {
+                    EntryKind::Dir
+                } else if entry.is_symlink() {
+                    EntryKind::Symlink
+                } else {
+                    EntryKind::File
+                };
+                entries.insert(
+                    path.clone(),
+                    ContentEntry {
+                        path,
+                        kind,
+                        size: entry.size(),
+                        crc32: entry.crc32(),
+                        source: id.clone(),
+                        deleted: false,
+                    },
+                );
+            }
+        }
+
+        if let Some(man) = load_manifest(app_root, id)? {
+            for del_key in man.deleted_paths {
+                if let Some((scope, rel)) = del_key.split_once("::") {
+                    let path = format!("{scope}/{rel}");
+                    let kind = entries.get(&path).map_or(EntryKind::File, |e| e.kind);
+                    entries.insert(
+                        path.clone(),
+                        ContentEntry {
+                            path,
+                            kind,
+                            size: 0,
+                            crc32: 0,
+                            source: id.clone(),
+                            deleted: true,
+                        },
+                    );
+                }
+            }
+        }
     }
+
+    let mut contents: Vec<ContentEntry> = entries.into_values().collect();
+    contents.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(contents)
 }
 
 pub fn format_bytes(bytes: u64) -> String {
@@ -118,118 +334,487 @@ fn matches_exclusion(path: &Path, exclusions: &[String]) -> bool {
 // ZIP helpers
 // -----------------------------------------------------------------------------
 
-fn create_zip_archive(archive_path: &Path, source_dir: &Path) -> Result<(), String> {
-    let file = File::create(archive_path)
-        .map_err(|e| format!("Failed to create zip file: {}", e))?;
+fn entry_options(mode: u32, mtime: u64) -> FullFileOptions<'static> {
+    let zip_time = match Local.timestamp_opt(mtime as i64, 0).single() {
+        Some(dt) => match ZipDateTime::from_date_and_time(
+            dt.year() as u16,
+            dt.month() as u8,
+            dt.day() as u8,
+            dt.hour() as u8,
+            dt.minute() as u8,
+            dt.second() as u8,
+        ) {
+            Ok(zt) => zt,
+            Err(_) => ZipDateTime::default_for_write(),
+        },
+        None => ZipDateTime::default_for_write(),
+    };
 
-    let mut zip = zip::ZipWriter::new(file);
-    let walk_dir = WalkDir::new(source_dir);
+    let mut options = FullFileOptions::default()
+        .unix_permissions(mode)
+        .last_modified_time(zip_time);
 
-    for entry in walk_dir.into_iter().filter_map(|e| e.ok()) {
-        let path = entry.path();
-        let name = path
-            .strip_prefix(source_dir)
-            .map_err(|e| format!("Path prefix error: {}", e))?
-            .to_string_lossy()
-            .replace("\\", "/");
-
-        let meta = fs::metadata(path).ok();
-
-        let zip_time = if let Some(ref m) = meta {
-            if let Ok(mtime) = m.modified() {
-                let dt: chrono::DateTime<Local> = mtime.into();
-                match ZipDateTime::from_date_and_time(
-                    dt.year() as u16,
-                    dt.month() as u8,
-                    dt.day() as u8,
-                    dt.hour() as u8,
-                    dt.minute() as u8,
-                    dt.second() as u8,
-                ) {
-                    Ok(zt) => zt,
-                    Err(_) => ZipDateTime::default_for_write(),
+    // Extended timestamp (0x5455): mtime exato em segundos, o DOS time só tem 2s
+    if let Ok(secs) = u32::try_from(mtime) {
+        let mut data = vec![1u8];
+        data.extend_from_slice(&secs.to_le_bytes());
+        options.add_extra_data(0x5455, data.into_boxed_slice(), false).ok();
+    }
+
+    options
+}
+
+fn zip_entry_options(meta: &fs::Metadata) -> FullFileOptions<'static> {
+    entry_options(entry_mode(meta), mtime_secs(meta))
+}
+
+// Codec e nível para um arquivo: override por extensão, senão o padrão do repositório
+fn compression_for(cfg: &CompressionConfig, name: &str) -> (zip::CompressionMethod, Option<i64>) {
+    let codec = Path::new(name)
+        .extension()
+        .and_then(|e| cfg.overrides.get(&e.to_string_lossy().to_lowercase()).copied())
+        .unwrap_or(cfg.codec);
+
+    match codec {
+        Codec::Store => (zip::CompressionMethod::Stored, None),
+        Codec::Deflate => {
+            let level = cfg.level.unwrap_or(match cfg.profile {
+                CompressionProfile::Fast => 1,
+                CompressionProfile::Balanced => 6,
+                CompressionProfile::Max => 9,
+            });
+            (zip::CompressionMethod::Deflated, Some(level.clamp(1, 9)))
+        }
+        Codec::Zstd => {
+            let level = cfg.level.unwrap_or(match cfg.profile {
+                CompressionProfile::Fast => 1,
+                CompressionProfile::Balanced => 3,
+                CompressionProfile::Max => 19,
+            });
+            (zip::CompressionMethod::Zstd, Some(level.clamp(1, 22)))
+        }
+    }
+}
+
+/// Entrada do snapshot: nome dentro do zip ("Scope/rel/path") e origem real.
+struct ArchiveEntry {
+    name: String,
+    source: PathBuf,
+}
+
+// Arquivos até esse tamanho são comprimidos em paralelo, em memória;
+// os maiores seguem em streaming na thread de escrita
+const PARALLEL_MAX_BYTES: u64 = 16 * 1024 * 1024;
+
+fn write_zip_entry<W: io::Write + io::Seek>(
+    zip: &mut zip::ZipWriter<W>,
+    entry: &ArchiveEntry,
+    meta: &fs::Metadata,
+    compression: &CompressionConfig,
+) -> BlkResult<()> {
+    let path = entry.source.as_path();
+    let name = entry.name.as_str();
+    let options = zip_entry_options(meta);
+
+    if meta.file_type().is_symlink() {
+        let target = fs::read_link(path).at("read link", path)?;
+        zip.add_symlink(name, target.to_string_lossy().replace("\\", "/"), options)
+            .at("add symlink to zip", path)?;
+    } else if meta.is_dir() {
+        let options = options.compression_method(zip::CompressionMethod::Stored);
+        zip.add_directory(name, options)
+            .at("add directory to zip", path)?;
+    } else {
+        let (method, level) = compression_for(compression, name);
+        let options = options
+            .compression_method(method)
+            .compression_level(level)
+            .large_file(meta.len() > 0xffffffff);
+
+        zip.start_file(name, options).at("add file to zip", path)?;
+
+        let mut f = File::open(path).at("open", path)?;
+
+        io::copy(&mut f, zip).at("compress", path)?;
+    }
+
+    Ok(())
+}
+
+// Zip de uma entrada só, em memória; depois é anexado inteiro com merge_archive
+fn compress_to_memory(
+    entry: &ArchiveEntry,
+    meta: &fs::Metadata,
+    compression: &CompressionConfig,
+) -> BlkResult<Vec<u8>> {
+    let mut zip = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+    write_zip_entry(&mut zip, entry, meta, compression)?;
+    let cursor = zip.finish().at("compress", &entry.source)?;
+    Ok(cursor.into_inner())
+}
+
+/// Lê direto das pastas reais, sem cópia intermediária em staging. Arquivos
+/// pequenos e médios são comprimidos por um pool de threads e gravados na
+/// ordem original; o resultado é um zip comum, legível pelo extrator de sempre.
+/// Cancelar devolve erro e o writer é descartado junto com o que já foi escrito.
+fn create_zip_archive<W: io::Write + io::Seek>(
+    out: W,
+    label: &str,
+    entries: &[ArchiveEntry],
+    compression: &CompressionConfig,
+    cancel: &CancelToken,
+    tx: &EventSender,
+) -> BlkResult<W> {
+    let metas = entries
+        .iter()
+        .map(|e| fs::symlink_metadata(&e.source).at("read metadata of", &e.source))
+        .collect::<BlkResult<Vec<_>>>()?;
+    let file_bytes = |m: &fs::Metadata| if m.is_file() { m.len() } else { 0 };
+    let total_bytes = metas.iter().map(file_bytes).sum();
+    let mut counter = FileCounter::new(tx, entries.len(), total_bytes);
+    let parallel: Vec<bool> = metas
+        .iter()
+        .map(|m| m.is_file() && m.len() <= PARALLEL_MAX_BYTES)
+        .collect();
+
+    let mut zip = zip::ZipWriter::new(out);
+
+    let workers = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(entries.len().max(1));
+    // Limita quantos resultados ficam em memória esperando a vez
+    let window = workers * 2;
+
+    let stopped = || BlkError::Aborted("Compression workers stopped".into());
+
+    thread::scope(|scope| -> BlkResult<()> {
+        let (job_tx, job_rx) = mpsc::channel::<usize>();
+        let job_rx = Arc::new(Mutex::new(job_rx));
+        let (res_tx, res_rx) = mpsc::channel::<(usize, BlkResult<Vec<u8>>)>();
+
+        for _ in 0..workers {
+            let job_rx = Arc::clone(&job_rx);
+            let res_tx = res_tx.clone();
+            let metas = &metas;
+            scope.spawn(move || loop {
+                let job = job_rx.lock().map(|rx| rx.recv());
+                let idx = match job {
+                    Ok(Ok(i)) => i,
+                    _ => break,
+                };
+                let result = compress_to_memory(&entries[idx], &metas[idx], compression);
+                if res_tx.send((idx, result)).is_err() {
+                    break;
                 }
-            } else {
-                ZipDateTime::default_for_write()
+            });
+        }
+        drop(res_tx);
+
+        let mut next_job = 0;
+        let mut ready: HashMap<usize, BlkResult<Vec<u8>>> = HashMap::new();
+
+        for (i, entry) in entries.iter().enumerate() {
+            cancel.check()?;
+            while next_job < entries.len() && next_job < i + window {
+                if parallel[next_job] {
+                    job_tx.send(next_job).map_err(|_| stopped())?;
+                }
+                next_job += 1;
             }
-        } else {
-            ZipDateTime::default_for_write()
-        };
 
-        if path.is_dir() {
-            if !name.is_empty() {
-                let options = FileOptions::<()>::default()
-                    .compression_method(zip::CompressionMethod::Stored)
-                    .unix_permissions(0o755)
-                    .last_modified_time(zip_time);
-                zip.add_directory(&name, options)
-                    .map_err(|e| format!("Zip dir error: {}", e))?;
+            if !parallel[i] {
+                write_zip_entry(&mut zip, entry, &metas[i], compression)?;
+                counter.advance(file_bytes(&metas[i]));
+                continue;
             }
-        } else {
-            let len = meta.as_ref().map(|m| m.len()).unwrap_or(0);
-            let options = FileOptions::<()>::default()
-                .compression_method(zip::CompressionMethod::Zstd)
-                .unix_permissions(0o755)
-                .large_file(len > 0xffffffff)
-                .last_modified_time(zip_time);
 
-            zip.start_file(&name, options)
-                .map_err(|e| format!("Zip start file error: {}", e))?;
+            while !ready.contains_key(&i) {
+                let (idx, result) = res_rx.recv().map_err(|_| stopped())?;
+                ready.insert(idx, result);
+            }
+            let buf = ready.remove(&i).unwrap_or_else(|| Err(stopped()))?;
+            let part = zip::ZipArchive::new(io::Cursor::new(buf)).at("compress", &entry.source)?;
+            zip.merge_archive(part).at("compress", &entry.source)?;
+            counter.advance(file_bytes(&metas[i]));
+        }
 
-            let mut f = File::open(path)
-                .map_err(|e| format!("Failed to open file {:?}: {}", path, e))?;
+        drop(job_tx);
+        Ok(())
+    })?;
 
-            io::copy(&mut f, &mut zip)
-                .map_err(|e| format!("Write zip error (streaming): {}", e))?;
+    zip.finish().at("finalize snapshot", label)
+}
+
+/// Grava uma camada nova no armazenamento; só fica visível se tudo deu certo.
+fn store_zip_archive(
+    store: &dyn SnapshotStore,
+    id: &str,
+    entries: &[ArchiveEntry],
+    compression: &CompressionConfig,
+    cancel: &CancelToken,
+    tx: &EventSender,
+) -> BlkResult<()> {
+    let writer = store.create(id).at("create snapshot", id)?;
+    create_zip_archive(writer, id, entries, compression, cancel, tx)?
+        .commit()
+        .at("store snapshot", id)
+}
+
+// mtime gravado na entrada: extended timestamp quando existe, senão o DOS time
+fn zip_entry_mtime<R: io::Read>(file: &zip::read::ZipFile<'_, R>) -> Option<u64> {
+    for field in file.extra_data_fields() {
+        if let zip::ExtraField::ExtendedTimestamp(ts) = field {
+            if let Some(secs) = ts.mod_time() {
+                return Some(secs as u64);
+            }
         }
     }
 
-    zip.finish()
-        .map_err(|e| format!("Failed to finalize zip: {}", e))?;
+    let dt = file.last_modified()?;
+    Local
+        .with_ymd_and_hms(
+            dt.year() as i32,
+            dt.month() as u32,
+            dt.day() as u32,
+            dt.hour() as u32,
+            dt.minute() as u32,
+            dt.second() as u32,
+        )
+        .earliest()
+        .and_then(|t| u64::try_from(t.timestamp()).ok())
+}
+
+/// Regrava uma entrada de outro zip com outro nome, preservando tipo, modo e
+/// mtime (raw_copy_file perde o extended timestamp e o tipo symlink).
+pub(crate) fn reencode_zip_entry<R: io::Read, W: io::Write + io::Seek>(
+    src: &mut zip::read::ZipFile<'_, R>,
+    dst: &mut zip::ZipWriter<W>,
+    name: &str,
+    compression: &CompressionConfig,
+) -> BlkResult<()> {
+    let mode = src.unix_mode().map(|m| m & 0o7777).unwrap_or(0o644);
+    let options = entry_options(mode, zip_entry_mtime(src).unwrap_or(0));
+
+    if src.is_dir() {
+        let options = options.compression_method(zip::CompressionMethod::Stored);
+        dst.add_directory(name, options).at("add directory to zip", name)?;
+    } else if src.is_symlink() {
+        let mut target = String::new();
+        io::Read::read_to_string(src, &mut target).at("read link target of", name)?;
+        dst.add_symlink(name, target, options).at("add symlink to zip", name)?;
+    } else {
+        let (method, level) = compression_for(compression, name);
+        let options = options
+            .compression_method(method)
+            .compression_level(level)
+            .large_file(src.size() > 0xffffffff);
+        dst.start_file(name, options).at("add file to zip", name)?;
+        io::copy(src, dst).at("copy zip entry", name)?;
+    }
 
     Ok(())
 }
 
-fn extract_zip_archive(archive_path: &Path, dest_dir: &Path) -> Result<(), String> {
-    let file = File::open(archive_path).map_err(|e| format!("Failed to open zip: {}", e))?;
+/// Descobre o destino real de uma entrada do zip ("ScopeName/Path/To/File.txt").
+/// Retorna None para a pasta do próprio scope, que não é uma entrada rastreada.
+fn resolve_destination(config: &BlkConfig, rel: &Path) -> Option<PathBuf> {
+    let mut it = rel.iter();
+    let scope_name = it.next()?.to_string_lossy().to_string();
+    let rest: PathBuf = it.collect();
+
+    if let Some(base) = config.path_map.get(&scope_name) {
+        if rest.as_os_str().is_empty() {
+            return None;
+        }
+        Some(base.join(rest))
+    } else {
+        // Fallback para raiz antiga
+        config.path_map.get("Root").map(|root_base| root_base.join(rel))
+    }
+}
+
+/// Plano de restauração: para cada camada, as entradas do zip (índice, destino)
+/// que sobrevivem às camadas de cima e aos `deleted_paths`.
+struct RestorePlan {
+    layers: Vec<Vec<(usize, PathBuf)>>,
+    deleted: Vec<PathBuf>,
+    total_bytes: u64,
+}
 
-    let mut archive = zip::ZipArchive::new(file)
-        .map_err(|e| format!("Failed to read zip archive: {}", e))?;
+/// Calcula a camada mais alta que fornece cada caminho, aplicando os
+/// `deleted_paths` na ordem da cadeia. Só lê o diretório central dos zips.
+fn plan_restore(
+    app_root: &Path,
+    store: &dyn SnapshotStore,
+    config: &BlkConfig,
+    ids: &[String],
+) -> BlkResult<RestorePlan> {
+    let mut winners: HashMap<PathBuf, (usize, usize, u64)> = HashMap::new();
+    let mut deleted: HashSet<PathBuf> = HashSet::new();
+
+    // Camada faltando (storage desmontado, arquivo apagado) aborta aqui, antes
+    // do wipe: restaurar sem ela apagaria os scopes e reportaria sucesso.
+    if let Some(id) = ids.iter().find(|id| !store.contains(id)) {
+        return Err(BlkError::NotFound(format!("{id}: snapshot layer is missing")));
+    }
 
-    for i in 0..archive.len() {
-        let mut file = archive
-            .by_index(i)
-            .map_err(|e| format!("Zip index error: {}", e))?;
+    for (layer, id) in ids.iter().enumerate() {
+        let file = store.open(id).at("open snapshot", id)?;
+        let mut archive = zip::ZipArchive::new(file).at("read snapshot", id)?;
 
-        let outpath = match file.enclosed_name() {
-            Some(path) => dest_dir.join(path),
-            None => continue,
-        };
+        for i in 0..archive.len() {
+            let entry = archive.by_index_raw(i).at("read snapshot", id)?;
+            let dest = match entry.enclosed_name() {
+                Some(rel) => match resolve_destination(config, &rel) {
+                    Some(p) => p,
+                    None => continue,
+                },
+                None => continue,
+            };
+            deleted.remove(&dest);
+            winners.insert(dest, (layer, i, entry.size()));
+        }
 
-        if file.name().ends_with('/') {
-            fs::create_dir_all(&outpath)
-                .map_err(|e| format!("Failed to create dir {:?}: {}", outpath, e))?;
-        } else {
-            if let Some(p) = outpath.parent() {
-                if !p.exists() {
-                    fs::create_dir_all(p)
-                        .map_err(|e| format!("Failed to create parent dir: {}", e))?;
+        // Apply manifest specific deletions (files deleted in this delta)
+        if let Some(man) = load_manifest(app_root, id)? {
+            for del_key in man.deleted_paths {
+                if let Some((scope, rel)) = del_key.split_once("::") {
+                    if let Some(base) = config.path_map.get(scope) {
+                        let target = base.join(rel);
+                        winners.remove(&target);
+                        deleted.insert(target);
+                    }
                 }
             }
-            
-            #[cfg(unix)]
-            {
-                use std::os::unix::fs::PermissionsExt;
+        }
+    }
+
+    let mut layers: Vec<Vec<(usize, PathBuf)>> = vec![Vec::new(); ids.len()];
+    let mut total_bytes = 0;
+    for (dest, (layer, index, size)) in winners {
+        layers[layer].push((index, dest));
+        total_bytes += size;
+    }
+    // Ordem do zip: leitura sequencial e pastas antes do conteúdo
+    for entries in &mut layers {
+        entries.sort_by_key(|(index, _)| *index);
+    }
+
+    Ok(RestorePlan {
+        layers,
+        deleted: deleted.into_iter().collect(),
+        total_bytes,
+    })
+}
+
+/// Estado da reconstrução que atravessa todas as camadas.
+struct Extraction<'a> {
+    counter: FileCounter<'a>,
+    /// Permissões de pastas, aplicadas no fim da cadeia para que uma pasta
+    /// somente-leitura não bloqueie as camadas seguintes.
+    dir_modes: Vec<(PathBuf, u32)>,
+    /// Tudo que a reconstrução criou; é o que sai se o restore for desfeito.
+    restored: Vec<PathBuf>,
+    /// Pastas dos scopes (já expandidas), limite da checagem de symlinks.
+    roots: Vec<PathBuf>,
+    cancel: &'a CancelToken,
+}
+
+/// Alguma pasta entre o scope e `dest` é um symlink? Uma camada pode trazer
+/// o link e depois entradas "dentro" dele, que iriam parar fora do scope.
+fn through_symlink(roots: &[PathBuf], dest: &Path) -> bool {
+    let root = match roots
+        .iter()
+        .filter(|r| dest.starts_with(r))
+        .max_by_key(|r| r.as_os_str().len())
+    {
+        Some(r) => r,
+        None => return true,
+    };
+    dest.ancestors()
+        .skip(1)
+        .take_while(|a| *a != root.as_path())
+        .any(|a| fs::symlink_metadata(a).is_ok_and(|m| m.file_type().is_symlink()))
+}
+
+/// Extrai as entradas planejadas direto no destino do seu scope, sem pasta temporária.
+/// Uma entrada que falha vai para o `report` e a camada segue; só um zip
+/// ilegível ou um cancelamento interrompem a camada inteira.
+fn restore_zip_entries(
+    file: Box<dyn ReadSeek>,
+    id: &str,
+    entries: &[(usize, PathBuf)],
+    run: &mut Extraction,
+    report: &mut Report,
+) -> BlkResult<()> {
+    let mut archive = zip::ZipArchive::new(file).at("read snapshot", id)?;
+
+    for (index, outpath) in entries {
+        run.cancel.check()?;
+        let mut file = match archive.by_index(*index).at("read snapshot", id) {
+            Ok(f) => f,
+            Err(e) => {
+                run.counter.advance(0);
+                report.fail(e);
+                continue;
+            }
+        };
+        run.counter.advance(file.size());
+        let outpath = outpath.clone();
+
+        if through_symlink(&run.roots, &outpath) {
+            report.fail(BlkError::Integrity(format!(
+                "{}: a parent folder is a symbolic link, entry not restored",
+                outpath.to_string_lossy()
+            )));
+            continue;
+        }
+
+        if file.is_dir() {
+            report.check(clear_destination(&outpath, true).at("replace", &outpath));
+            if report.check(fs::create_dir_all(&outpath).at("create directory", &outpath)).is_some() {
+                run.restored.push(outpath.clone());
                 if let Some(mode) = file.unix_mode() {
-                    let _ = fs::set_permissions(&outpath, fs::Permissions::from_mode(mode));
+                    run.dir_modes.push((outpath, mode));
                 }
             }
+            continue;
+        }
 
-            let mut outfile = File::create(&outpath)
-                .map_err(|e| format!("Failed to create file {:?}: {}", outpath, e))?;
+        if let Some(p) = outpath.parent() {
+            if !p.exists() && report.check(fs::create_dir_all(p).at("create directory", p)).is_none() {
+                continue;
+            }
+        }
 
-            io::copy(&mut file, &mut outfile)
-                .map_err(|e| format!("Failed to extract file: {}", e))?;
+        // Overwrites because we are layering up (inclusive arquivos somente-leitura)
+        if report.check(clear_destination(&outpath, false).at("replace", &outpath)).is_none() {
+            continue;
+        }
+        run.restored.push(outpath.clone());
+
+        if file.is_symlink() {
+            let mut target = String::new();
+            let linked = io::Read::read_to_string(&mut file, &mut target)
+                .at("read link target of", &outpath)
+                .and_then(|_| create_symlink(Path::new(&target), &outpath).at("create link", &outpath));
+            report.check(linked);
+            continue;
+        }
+
+        let extracted = File::create(&outpath)
+            .at("create", &outpath)
+            .and_then(|mut outfile| io::copy(&mut file, &mut outfile).at("extract", &outpath));
+        if report.check(extracted).is_none() {
+            continue;
+        }
+
+        if let Some(secs) = zip_entry_mtime(&file) {
+            report.check(set_file_mtime(&outpath, secs).at("set modification time of", &outpath));
+        }
+        if let Some(mode) = file.unix_mode() {
+            report.check(apply_mode(&outpath, mode).at("set permissions of", &outpath));
         }
     }
 
@@ -240,45 +825,215 @@ fn extract_zip_archive(archive_path: &Path, dest_dir: &Path) -> Result<(), Strin
 // NUCLEAR WIPE HELPER
 // -----------------------------------------------------------------------------
 
-fn nuke_scopes(config: &BlkConfig, tx: &mpsc::Sender<(f32, String)>) -> usize {
+const TRASH_PREFIX: &str = ".blk-trash-";
+
+/// O wipe não apaga: move para uma pasta `.blk-trash-*` na raiz do próprio
+/// scope (mesmo disco, rename barato). O restore esvazia a lixeira quando
+/// termina, ou devolve tudo para o lugar se for cancelado.
+struct Trash {
+    stamp: u64,
+    /// (raiz do scope, pasta da lixeira)
+    dirs: Vec<(PathBuf, PathBuf)>,
+    /// Pastas que existiam antes do wipe. Ficam no lugar; o rollback recria
+    /// as que a extração tirou (um arquivo da camada no lugar de uma pasta).
+    folders: Vec<PathBuf>,
+}
+
+impl Trash {
+    fn new() -> Self {
+        Trash {
+            stamp: now_unix(),
+            dirs: Vec::new(),
+            folders: Vec::new(),
+        }
+    }
+
+    fn keep_folder(&mut self, path: &Path) {
+        self.folders.push(path.to_path_buf());
+    }
+
+    fn take(&mut self, root: &Path, path: &Path) -> BlkResult<()> {
+        let dir = match self.dirs.iter().find(|(r, _)| r == root) {
+            Some((_, d)) => d.clone(),
+            None => {
+                let d = root.join(format!("{TRASH_PREFIX}{}", self.stamp));
+                fs::create_dir_all(&d).at("create directory", &d)?;
+                self.dirs.push((root.to_path_buf(), d.clone()));
+                d
+            }
+        };
+        let dest = dir.join(path.strip_prefix(root).unwrap_or(path));
+        if let Some(p) = dest.parent() {
+            fs::create_dir_all(p).at("create directory", p)?;
+        }
+        // Outro disco montado dentro do scope: apagar tiraria o arquivo do
+        // rollback, então o restore para aqui e devolve o que já foi movido
+        fs::rename(path, &dest).map_err(|e| BlkError::Aborted(format!(
+            "{}: could not move it aside for the restore ({e}); nothing was deleted",
+            path.to_string_lossy()
+        )))
+    }
+
+    /// Restore concluído: o conteúdo antigo não é mais necessário. Se algo
+    /// falhou no caminho ele fica, já que pode ser a única cópia do que não
+    /// foi restaurado direito.
+    fn empty(self, report: &mut Report) {
+        if !report.failures.is_empty() {
+            for (_, dir) in &self.dirs {
+                report.fail(BlkError::Integrity(format!(
+                    "The previous files were kept in {}",
+                    dir.display()
+                )));
+            }
+            return;
+        }
+        for (_, dir) in &self.dirs {
+            report.check(fs::remove_dir_all(dir).at("remove", dir));
+        }
+    }
+
+    /// Desfaz o restore: tira o que foi extraído e devolve o conteúdo antigo.
+    /// Uma lixeira que não voltou inteira fica no disco para recuperação manual.
+    fn roll_back(self, restored: &[PathBuf], report: &mut Report) {
+        // Mais profundos primeiro; pastas só saem se ficaram vazias
+        for path in restored.iter().rev() {
+            match fs::symlink_metadata(path) {
+                Ok(meta) if meta.is_dir() => {
+                    let _ = fs::remove_dir(path);
+                }
+                Ok(_) => {
+                    report.check(fs::remove_file(path).at("remove", path));
+                }
+                Err(_) => {}
+            }
+        }
+
+        for (root, dir) in &self.dirs {
+            let before = report.failures.len();
+            for entry in WalkDir::new(dir) {
+                let entry = match entry {
+                    Ok(e) => e,
+                    Err(e) => {
+                        report.fail(walk_error(e));
+                        continue;
+                    }
+                };
+                if entry.file_type().is_dir() {
+                    continue;
+                }
+                let dest = root.join(entry.path().strip_prefix(dir).unwrap_or(entry.path()));
+                let moved = match dest.parent() {
+                    Some(p) => fs::create_dir_all(p).at("create directory", p),
+                    None => Ok(()),
+                }
+                .and_then(|_| fs::rename(entry.path(), &dest).at("put back", &dest));
+                report.check(moved);
+            }
+            if report.failures.len() == before {
+                report.check(fs::remove_dir_all(dir).at("remove", dir));
+            } else {
+                report.fail(BlkError::Integrity(format!(
+                    "Some files could not be put back; they are still in {}",
+                    dir.display()
+                )));
+            }
+        }
+
+        for folder in &self.folders {
+            if !folder.is_dir() {
+                report.check(fs::create_dir_all(folder).at("create directory", folder));
+            }
+        }
+    }
+}
+
+/// Lixeiras de um restore que não terminou (processo morto no meio). Nunca são
+/// apagadas automaticamente: podem ser a única cópia do conteúdo antigo.
+fn leftover_trash(config: &BlkConfig, scopes: &[String]) -> Vec<PathBuf> {
+    scopes
+        .iter()
+        .filter_map(|s| config.path_map.get(s))
+        .filter_map(|root| fs::read_dir(root).ok())
+        .flat_map(|dir| dir.filter_map(|e| e.ok()))
+        .filter(|e| e.file_name().to_string_lossy().starts_with(TRASH_PREFIX))
+        .map(|e| e.path())
+        .collect()
+}
+
+fn nuke_scopes(
+    config: &BlkConfig,
+    scopes: &[String],
+    exclusions: &[String],
+    trash: &mut Trash,
+    cancel: &CancelToken,
+    tx: &EventSender,
+    report: &mut Report,
+) -> BlkResult<usize> {
     let mut deleted_count = 0;
-    
-    // Itera por todos os caminhos configurados (Scopes)
-    for (scope_name, root) in &config.path_map {
+
+    // Só limpa os scopes do set alvo; o resto do path_map fica intacto
+    for scope_name in scopes {
+        let root = match config.path_map.get(scope_name) {
+            Some(r) => r,
+            None => continue,
+        };
         if !root.exists() { continue; }
 
-        tx.send((0.0, format!("Nuking scope: {}...", scope_name))).ok();
-        
-        // Pega itens de nível superior para não apagar a pasta raiz em si, apenas conteúdo
-        if let Ok(read_dir) = fs::read_dir(root) {
-            for entry in read_dir.flatten() {
-                let path = entry.path();
-                
-                // CRÍTICO: Não apagar .blk, .git, etc.
-                if should_ignore(&path) { 
-                    continue; 
+        tx.send(EngineEvent::phase(0.0, format!("Nuking scope: {}...", scope_name))).ok();
+
+        // Apaga apenas o que o scan_state rastrearia (mesmos filtros)
+        for entry in WalkDir::new(root) {
+            let entry = match entry {
+                Ok(e) => e,
+                Err(e) => {
+                    report.fail(walk_error(e));
+                    continue;
                 }
+            };
+            let path = entry.path();
 
-                if path.is_dir() {
-                    if fs::remove_dir_all(&path).is_ok() {
-                        deleted_count += 1;
-                    }
-                } else {
-                    if fs::remove_file(&path).is_ok() {
-                        deleted_count += 1;
+            // CRÍTICO: Não apagar .blk, .git, etc.
+            if should_ignore(path) {
+                continue;
+            }
+            if matches_exclusion(path, exclusions) {
+                continue;
+            }
+
+            // Pastas restauradas como somente-leitura precisam voltar a aceitar remoção
+            if entry.file_type().is_dir() {
+                if let Ok(meta) = entry.metadata() {
+                    let mode = entry_mode(&meta);
+                    if mode & 0o700 != 0o700 {
+                        report.check(apply_mode(path, mode | 0o700).at("make writable", path));
                     }
                 }
+                if path != root.as_path() {
+                    trash.keep_folder(path);
+                }
+                continue;
             }
+
+            // Arquivos e symlinks; pastas vazias só saem no prune, depois do rebuild
+            cancel.check()?;
+            trash.take(root, path)?;
+            deleted_count += 1;
         }
     }
-    deleted_count
+
+    Ok(deleted_count)
 }
 
-fn prune_empty_dirs(config: &BlkConfig) {
+/// Tira as pastas que ficaram vazias e que nenhuma camada trouxe (`keep`).
+fn prune_empty_dirs(config: &BlkConfig, scopes: &[String], exclusions: &[String], keep: &HashSet<&Path>) {
     for _ in 0..3 {
         let mut changes = false;
-        
-        for (_scope_name, root) in &config.path_map {
+
+        for scope_name in scopes {
+            let root = match config.path_map.get(scope_name) {
+                Some(r) => r,
+                None => continue,
+            };
             if !root.exists() { continue; }
 
             for entry in WalkDir::new(root).contents_first(true).into_iter().filter_map(|e| e.ok()) {
@@ -286,7 +1041,9 @@ fn prune_empty_dirs(config: &BlkConfig) {
                 
                 if path.is_dir() {
                     if path == root { continue; }
+                    if keep.contains(path) { continue; }
                     if should_ignore(path) { continue; }
+                    if matches_exclusion(path, exclusions) { continue; }
 
                     if fs::remove_dir(path).is_ok() {
                         changes = true;
@@ -318,6 +1075,15 @@ fn prune_empty_dirs(config: &BlkConfig) {
 // Hashing & Lazy Scan
 // -----------------------------------------------------------------------------
 
+fn walk_error(e: walkdir::Error) -> BlkError {
+    let path = e.path().map(Path::to_path_buf).unwrap_or_default();
+    BlkError::Io {
+        op: "read",
+        path,
+        source: e.into(),
+    }
+}
+
 fn hash_file(path: &Path) -> String {
     let mut file = match File::open(path) {
         Ok(f) => f,
@@ -330,11 +1096,77 @@ fn hash_file(path: &Path) -> String {
     hex::encode(hasher.finalize())
 }
 
+fn hash_link(path: &Path) -> String {
+    match fs::read_link(path) {
+        Ok(target) => {
+            let mut hasher = Sha256::new();
+            hasher.update(target.to_string_lossy().replace("\\", "/").as_bytes());
+            hex::encode(hasher.finalize())
+        }
+        Err(_) => String::new(),
+    }
+}
+
+/// Lê o estado de uma entrada (sem seguir symlinks). Reaproveita o hash do
+/// baseline anterior quando tamanho e mtime não mudaram.
+fn read_entry(path: &Path, previous: Option<&FileEntry>) -> Option<FileEntry> {
+    let meta = fs::symlink_metadata(path).ok()?;
+    let modified = mtime_secs(&meta);
+    let mode = entry_mode(&meta);
+
+    if meta.file_type().is_symlink() {
+        return Some(FileEntry {
+            hash: hash_link(path),
+            size: meta.len(),
+            modified,
+            kind: EntryKind::Symlink,
+            mode,
+        });
+    }
+
+    if meta.is_dir() {
+        return Some(FileEntry {
+            hash: String::new(),
+            size: 0,
+            modified,
+            kind: EntryKind::Dir,
+            mode,
+        });
+    }
+
+    let size = meta.len();
+    let hash = match previous {
+        Some(old)
+            if old.kind == EntryKind::File && old.size == size && old.modified == modified =>
+        {
+            old.hash.clone()
+        }
+        _ => hash_file(path),
+    };
+
+    Some(FileEntry {
+        hash,
+        size,
+        modified,
+        kind: EntryKind::File,
+        mode,
+    })
+}
+
+// Modo 0 vem de baselines antigos e não conta como mudança
+fn entry_changed(old: &FileEntry, new: &FileEntry) -> bool {
+    old.kind != new.kind
+        || old.hash != new.hash
+        || (old.mode != 0 && new.mode != 0 && old.mode != new.mode)
+}
+
+/// Pastas ilegíveis durante a varredura vão para `failures`.
 fn scan_state(
     config: &BlkConfig,
     scopes: &[String],
     exclusions: &[String],
     previous_baseline: Option<&HashMap<String, FileEntry>>,
+    failures: &mut Vec<BlkError>,
 ) -> HashMap<String, FileEntry> {
     let mut map = HashMap::new();
 
@@ -346,7 +1178,10 @@ fn scan_state(
             for entry in WalkDir::new(root) {
                 let entry = match entry {
                     Ok(e) => e,
-                    Err(_) => continue,
+                    Err(e) => {
+                        failures.push(walk_error(e));
+                        continue;
+                    }
                 };
                 let path = entry.path();
 
@@ -357,48 +1192,19 @@ fn scan_state(
                     continue;
                 }
 
-                if path.is_file() {
-                    let rel = path
-                        .strip_prefix(root)
-                        .unwrap_or(path)
-                        .to_string_lossy()
-                        .replace("\\", "/");
-                    let key = format!("{}::{}", scope_name, rel);
-
-                    let (size, modified) = if let Ok(meta) = fs::metadata(path) {
-                        let m = meta
-                            .modified()
-                            .unwrap_or(UNIX_EPOCH)
-                            .duration_since(UNIX_EPOCH)
-                            .unwrap_or_default()
-                            .as_secs();
-                        (meta.len(), m)
-                    } else {
-                        (0, 0)
-                    };
-
-                    let hash = if let Some(prev_map) = previous_baseline {
-                        if let Some(old_entry) = prev_map.get(&key) {
-                            if old_entry.size == size && old_entry.modified == modified {
-                                old_entry.hash.clone()
-                            } else {
-                                hash_file(path)
-                            }
-                        } else {
-                            hash_file(path)
-                        }
-                    } else {
-                        hash_file(path)
-                    };
-
-                    map.insert(
-                        key,
-                        FileEntry {
-                            hash,
-                            size,
-                            modified,
-                        },
-                    );
+                let rel = path
+                    .strip_prefix(root)
+                    .unwrap_or(path)
+                    .to_string_lossy()
+                    .replace("\\", "/");
+                if rel.is_empty() {
+                    continue;
+                }
+                let key = format!("{}::{}", scope_name, rel);
+
+                let previous = previous_baseline.and_then(|prev| prev.get(&key));
+                if let Some(file_entry) = read_entry(path, previous) {
+                    map.insert(key, file_entry);
                 }
             }
         }
@@ -406,43 +1212,65 @@ fn scan_state(
     map
 }
 
-fn save_baseline(app_root: &Path, map: &HashMap<String, FileEntry>) {
-    let path = app_root.join(".blk").join("baseline.json");
-    let json = serde_json::to_string(map).unwrap_or("{}".into());
-    let _ = fs::write(path, json);
-}
+// -----------------------------------------------------------------------------
+// Outcome reporting
+// -----------------------------------------------------------------------------
 
-fn load_baseline(app_root: &Path) -> HashMap<String, FileEntry> {
-    let path = app_root.join(".blk").join("baseline.json");
-    if !path.exists() {
-        return HashMap::new();
+fn write_failure_log(app_root: &Path, report: &Report) -> io::Result<PathBuf> {
+    let path = app_root.join(".blk").join("last_report.log");
+    let mut text = format!("{}\n", report.summary);
+    for failure in &report.failures {
+        text.push_str(&format!("{failure}\n"));
     }
-    let txt = fs::read_to_string(path).unwrap_or("{}".into());
-    serde_json::from_str(&txt).unwrap_or_default()
+    fs::write(&path, text)?;
+    Ok(path)
+}
+
+/// Fecha uma operação com `Finished` ou `Error`. A lista completa de falhas
+/// por arquivo vai para `.blk/last_report.log`.
+pub fn send_outcome(app_root: &Path, tx: &EventSender, outcome: BlkResult<Report>) {
+    let event = match outcome {
+        Ok(summary) => {
+            if summary.failures.len() > 1 {
+                if let Ok(log) = write_failure_log(app_root, &summary) {
+                    let msg = format!("Full failure list in {}", log.to_string_lossy());
+                    tx.send(EngineEvent::Warning(msg)).ok();
+                }
+            }
+            EngineEvent::Finished { summary }
+        }
+        Err(e) => EngineEvent::Error(e),
+    };
+    tx.send(event).ok();
 }
 
 // -----------------------------------------------------------------------------
 // Auto-init
 // -----------------------------------------------------------------------------
 
-pub fn engine_auto_init(app_root: &Path, tx: mpsc::Sender<(f32, String)>) {
-    tx.send((10.0, "Creating .blk structure (v3.0 Nuke)...".into()))
+pub fn engine_auto_init(app_root: &Path, tx: EventSender) -> BlkResult<Report> {
+    tx.send(EngineEvent::Started { operation: "init" }).ok();
+    tx.send(EngineEvent::phase(10.0, format!("Creating .blk structure (format v{FORMAT_VERSION})...")))
         .ok();
 
     let blk = app_root.join(".blk");
     if blk.exists() {
-        tx.send((100.0, "Already initialized.".into())).ok();
-        return;
+        return Ok(Report::new("Already initialized."));
     }
 
-    fs::create_dir_all(blk.join("sets")).ok();
-    fs::create_dir_all(blk.join("snapshots")).ok();
+    let sets_dir = sets_dir(app_root);
+    fs::create_dir_all(&sets_dir).at("create directory", &sets_dir)?;
+    let _lock = RepoLock::acquire(app_root, "init")?;
 
     let mut path_map = HashMap::new();
-    path_map.insert("Root".to_string(), app_root.to_path_buf());
-    let config = BlkConfig { path_map };
-    let cfg_json = serde_json::to_string_pretty(&config).unwrap();
-    fs::write(blk.join("config.json"), cfg_json).ok();
+    path_map.insert("Root".to_string(), PathBuf::from(ROOT_VAR));
+    let config = BlkConfig {
+        path_map,
+        ..BlkConfig::default()
+    };
+    stamp_format(app_root)?;
+    save_config(app_root, &config)?;
+    let config = resolve_path_map(&config, app_root)?;
 
     let vanilla = SetManifest {
         id: "vanilla".into(),
@@ -453,65 +1281,87 @@ pub fn engine_auto_init(app_root: &Path, tx: mpsc::Sender<(f32, String)>) {
         created_at: now_unix(),
         deleted_paths: vec![],
     };
-    let van_json = serde_json::to_string_pretty(&vanilla).unwrap();
-    fs::write(blk.join("sets").join("vanilla.json"), van_json).ok();
-
-    tx.send((40.0, "Creating vanilla snapshot...".into())).ok();
-    let staging = blk.join("staging_vanilla");
-    if staging.exists() {
-        let _ = fs::remove_dir_all(&staging);
-    }
-    fs::create_dir_all(&staging).ok();
 
+    let store = open_store(app_root, &config)?;
+    tx.send(EngineEvent::phase(40.0, format!("Creating vanilla snapshot in {}...", store.location())))
+        .ok();
+    let mut report = Report::new("Initialization complete.");
+    let mut entries = Vec::new();
     for entry in WalkDir::new(app_root) {
         let entry = match entry {
             Ok(e) => e,
-            Err(_) => continue,
+            Err(e) => {
+                report.fail(walk_error(e));
+                continue;
+            }
         };
         let path = entry.path();
-        if should_ignore(path) {
+        if path == app_root || should_ignore(path) {
             continue;
         }
-        if path.is_file() {
-            if let Ok(rel) = path.strip_prefix(app_root) {
-                let dest = staging.join(rel);
-                if let Some(parent) = dest.parent() {
-                    fs::create_dir_all(parent).ok();
-                }
-                let _ = fs::copy(path, dest);
-            }
+        if let Ok(rel) = path.strip_prefix(app_root) {
+            entries.push(ArchiveEntry {
+                name: rel.to_string_lossy().replace("\\", "/"),
+                source: path.to_path_buf(),
+            });
         }
     }
 
-    let vanilla_zip = blk.join("snapshots").join("vanilla.zip");
-    if let Err(e) = create_zip_archive(&vanilla_zip, &staging) {
-        tx.send((100.0, format!("❌ Zip error: {e}"))).ok();
-        return;
-    }
-    let _ = fs::remove_dir_all(&staging);
+    // Camada primeiro, manifesto depois: um set nunca aponta para zip inexistente
+    // Init não oferece cancelamento
+    store_zip_archive(store.as_ref(), "vanilla", &entries, &config.compression, &CancelToken::default(), &tx)?;
+    save_manifest(app_root, &vanilla)?;
 
-    tx.send((80.0, "Building baseline...".into())).ok();
-    let state = scan_state(&config, &vec!["Root".into()], &vec![], None);
-    save_baseline(app_root, &state);
+    tx.send(EngineEvent::phase(80.0, "Building baseline...")).ok();
+    let state = scan_state(&config, &["Root".into()], &[], None, &mut report.failures);
+    save_baseline(app_root, &state)?;
 
-    tx.send((100.0, "✅ Initialization complete.".into())).ok();
+    Ok(report)
 }
 
 // -----------------------------------------------------------------------------
 // Config update
 // -----------------------------------------------------------------------------
 
-pub fn engine_update_global_path(app_root: &Path, key: String, path: String) {
-    let config_path = app_root.join(".blk").join("config.json");
-    let mut config: BlkConfig = if config_path.exists() {
-        serde_json::from_str(&fs::read_to_string(&config_path).unwrap_or("{}".into()))
-            .unwrap_or_default()
-    } else {
-        BlkConfig::default()
-    };
-    config.path_map.insert(key, PathBuf::from(path));
-    let json = serde_json::to_string_pretty(&config).unwrap();
-    let _ = fs::write(config_path, json);
+pub fn engine_update_global_path(app_root: &Path, key: String, path: String) -> BlkResult<()> {
+    let _lock = RepoLock::acquire(app_root, "update config")?;
+    update_config(app_root, |config| {
+        config.path_map.insert(key, PathBuf::from(path));
+    })
+}
+
+/// Tira `key` do path_map.
+/// Recusa enquanto algum set usa `key`: sem o mapeamento, o restore desses
+/// sets mandaria o scope para dentro do Root (`${ROOT}/<key>/...`).
+pub fn engine_remove_global_path(app_root: &Path, key: &str) -> BlkResult<()> {
+    // Sob o lock: um save não cria um set com `key` entre a checagem e a gravação
+    let _lock = RepoLock::acquire(app_root, "update config")?;
+    let (sets, _) = load_manifests(app_root);
+    let users: Vec<&str> = sets
+        .iter()
+        .filter(|m| m.scopes.iter().any(|s| s == key))
+        .map(|m| m.name.as_str())
+        .collect();
+    if !users.is_empty() {
+        return Err(BlkError::Config(format!(
+            "Scope '{key}' is used by {} set(s): {}. Take it out of their config first",
+            users.len(),
+            users.join(", ")
+        )));
+    }
+    update_config(app_root, |config| {
+        config.path_map.remove(key);
+    })
+}
+
+pub fn engine_update_compression(app_root: &Path, profile: CompressionProfile) -> BlkResult<()> {
+    let _lock = RepoLock::acquire(app_root, "update config")?;
+    update_config(app_root, |config| config.compression.profile = profile)
+}
+
+pub fn engine_update_remote(app_root: &Path, remote: PathBuf) -> BlkResult<()> {
+    let _lock = RepoLock::acquire(app_root, "update config")?;
+    update_config(app_root, |config| config.remote = Some(remote))
 }
 
 pub fn engine_update_manifest(
@@ -519,44 +1369,45 @@ pub fn engine_update_manifest(
     id: String,
     scopes: Vec<String>,
     exclusions: Vec<String>,
-    tx: mpsc::Sender<(f32, String)>,
-) {
-    tx.send((0.0, "Saving config...".into())).ok();
-    let path = app_root.join(".blk").join("sets").join(format!("{id}.json"));
-
-    if let Ok(txt) = fs::read_to_string(&path) {
-        if let Ok(mut man) = serde_json::from_str::<SetManifest>(&txt) {
-            man.scopes = scopes;
-            man.exclusions = exclusions;
-            if let Ok(json) = serde_json::to_string_pretty(&man) {
-                fs::write(&path, json).ok();
-                tx.send((100.0, "✅ Config saved!".into())).ok();
-                return;
-            }
-        }
-    }
-    tx.send((100.0, "Config error.".into())).ok();
+    tx: EventSender,
+) -> BlkResult<Report> {
+    tx.send(EngineEvent::Started { operation: "update manifest" }).ok();
+    tx.send(EngineEvent::phase(0.0, "Saving config...")).ok();
+    let _lock = RepoLock::acquire(app_root, "update manifest")?;
+
+    let mut man = load_manifest(app_root, &id)?
+        .ok_or_else(|| BlkError::NotFound(format!("Set '{id}' not found")))?;
+    man.scopes = scopes;
+    man.exclusions = exclusions;
+    save_manifest(app_root, &man)?;
+    Ok(Report::new("Config saved!"))
 }
 
 // -----------------------------------------------------------------------------
 // Diff checking
 // -----------------------------------------------------------------------------
 
+/// Variável que não resolve ou baseline ilegível viram erro, em vez de um
+/// diff que acusa tudo como novo ou apagado.
 pub fn engine_check_changes(
     app_root: &Path,
     config: BlkConfig,
     scopes: Vec<String>,
     exclusions: Vec<String>,
-    tx: mpsc::Sender<DiffSummary>,
-) {
-    let baseline = load_baseline(app_root);
-    let current = scan_state(&config, &scopes, &exclusions, Some(&baseline));
+) -> BlkResult<DiffSummary> {
+    let config = resolve_path_map(&config, app_root)?;
+    let baseline = load_baseline(app_root)?;
+    let mut failures = Vec::new();
+    let current = scan_state(&config, &scopes, &exclusions, Some(&baseline), &mut failures);
+    if let Some(first) = failures.into_iter().next() {
+        return Err(first);
+    }
 
     let mut diff = DiffSummary::default();
     for (key, new_entry) in &current {
         match baseline.get(key) {
             Some(old_entry) => {
-                if new_entry.hash != old_entry.hash {
+                if entry_changed(old_entry, new_entry) {
                     diff.modified_files += 1;
                 }
             }
@@ -570,7 +1421,7 @@ pub fn engine_check_changes(
     }
     diff.is_dirty =
         diff.new_files > 0 || diff.modified_files > 0 || diff.deleted_files > 0;
-    tx.send(diff).ok();
+    Ok(diff)
 }
 
 // -----------------------------------------------------------------------------
@@ -583,121 +1434,143 @@ pub fn engine_restore_chain(
     ids: Vec<String>,
     scopes: Vec<String>,
     exclusions: Vec<String>,
-    tx: mpsc::Sender<(f32, String)>,
-) {
+    cancel: CancelToken,
+    tx: EventSender,
+) -> BlkResult<Report> {
+    tx.send(EngineEvent::Started { operation: "restore" }).ok();
+    let _lock = RepoLock::acquire(app_root, "restore")?;
+    let config = resolve_path_map(&config, app_root)?;
+
+    // Baseline anterior serve de cache de hash: arquivos restaurados voltam
+    // com o mtime original, então (size, mtime) batem para o que não mudou.
+    // É só cache: se estiver ilegível, tudo é rehashado.
+    let previous = load_baseline(app_root).unwrap_or_default();
+
+    // 0. PLAN (antes do wipe: um zip corrompido aborta sem apagar nada)
+    tx.send(EngineEvent::phase(0.0, "Planning restore...")).ok();
+    let store = open_store(app_root, &config)?;
+    let plan = plan_restore(app_root, store.as_ref(), &config, &ids)?;
+    for dir in leftover_trash(&config, &scopes) {
+        tx.send(EngineEvent::Warning(format!(
+            "Files from an interrupted restore are still in {}",
+            dir.display()
+        )))
+        .ok();
+    }
+
     // 1. NUCLEAR WIPE
-    tx.send((0.0, "☢ NUCLEAR WIPE INITIATED ☢".into())).ok();
+    tx.send(EngineEvent::phase(0.0, "☢ NUCLEAR WIPE INITIATED ☢")).ok();
     thread::sleep(Duration::from_millis(500)); // Dramatic pause/safety
-    let items_removed = nuke_scopes(&config, &tx);
-    tx.send((10.0, format!("Wiped {} items. Starting rebuild...", items_removed))).ok();
-
-    // 2. RECONSTRUCTION
-    let total_layers = ids.len().max(1) as f32;
-
-    for (idx, id) in ids.iter().enumerate() {
-        let label = format!("Unpacking Layer {}/{} ({})", idx + 1, ids.len(), id);
-        tx.send((10.0 + ((idx as f32 / total_layers) * 80.0), label.clone())).ok();
-
-        let archive = app_root
-            .join(".blk")
-            .join("snapshots")
-            .join(format!("{id}.zip"));
+    let mut report = Report::new("Restore completed (Nuclear Clean)!");
+    let mut trash = Trash::new();
+    let mut run = Extraction {
+        counter: FileCounter::new(&tx, plan.layers.iter().map(Vec::len).sum(), plan.total_bytes),
+        dir_modes: Vec::new(),
+        restored: Vec::new(),
+        roots: config.path_map.values().cloned().collect(),
+        cancel: &cancel,
+    };
 
-        if archive.exists() {
-            // Extração temporária para separar scopes
-            let extract_root = app_root.join(".blk").join("tmp_extract").join(id);
-            if extract_root.exists() {
-                let _ = fs::remove_dir_all(&extract_root);
+    // 2. RECONSTRUCTION (cada caminho sai uma única vez, da camada mais alta)
+    let rebuilt = nuke_scopes(&config, &scopes, &exclusions, &mut trash, &cancel, &tx, &mut report)
+        .and_then(|removed| {
+            tx.send(EngineEvent::phase(10.0, format!("Wiped {} items. Starting rebuild...", removed))).ok();
+            rebuild_layers(store.as_ref(), &ids, &plan, &mut run, &tx, &mut report)
+        });
+
+    // Cancelado ou abortado no meio: o disco volta ao que era e o baseline
+    // nem é tocado
+    if let Err(e) = rebuilt {
+        let phase = match e {
+            BlkError::Cancelled => "Cancelled, putting files back...",
+            _ => "Restore failed, putting files back...",
+        };
+        tx.send(EngineEvent::phase(0.0, phase)).ok();
+        let mut rollback = Report::new("");
+        trash.roll_back(&run.restored, &mut rollback);
+        for failure in rollback.failures {
+            tx.send(EngineEvent::Warning(failure.to_string())).ok();
+        }
+        return Err(e);
+    }
+    let layer_dirs: HashSet<&Path> = run.restored.iter().map(PathBuf::as_path).collect();
+    prune_empty_dirs(&config, &scopes, &exclusions, &layer_dirs);
+
+    // Deleções fora da área limpa pelo wipe (pastas só se ficaram vazias)
+    for target in &plan.deleted {
+        if let Ok(meta) = fs::symlink_metadata(target) {
+            if meta.is_dir() {
+                // Pasta com conteúdo de outro scope/exclusão fica, sem ser falha
+                let _ = fs::remove_dir(target);
+            } else {
+                report.check(fs::remove_file(target).at("remove", target));
             }
-            fs::create_dir_all(&extract_root).ok();
+        }
+    }
 
-            if let Err(e) = extract_zip_archive(&archive, &extract_root) {
-                tx.send((100.0, format!("❌ Extract error for {id}: {e}")))
-                    .ok();
-                continue;
-            }
+    // Mais profundas primeiro: pai sem permissão de acesso não bloqueia o chmod das filhas
+    for (dir, mode) in run.dir_modes.iter().rev() {
+        if dir.is_dir() {
+            report.check(apply_mode(dir, *mode).at("set permissions of", dir));
+        }
+    }
+    trash.empty(&mut report);
 
-            // Move files to destination based on SCOPE prefix
-            for entry in WalkDir::new(&extract_root) {
-                let entry = match entry {
-                    Ok(e) => e,
-                    Err(_) => continue,
-                };
-                let path = entry.path();
-                if path.is_dir() {
-                    continue;
-                }
+    tx.send(EngineEvent::phase(99.0, "Regenerating baseline...")).ok();
+    let state = scan_state(&config, &scopes, &exclusions, Some(&previous), &mut report.failures);
+    save_baseline(app_root, &state)?;
+    if !report.failures.is_empty() {
+        report.summary = "Restore completed with errors".into();
+    }
+    Ok(report)
+}
 
-                // Arquivo no zip: "ScopeName/Path/To/File.txt"
-                let rel = match path.strip_prefix(&extract_root) {
-                    Ok(r) => r,
-                    Err(_) => continue,
-                };
-                let mut it = rel.iter();
-                let scope_os = match it.next() {
-                    Some(c) => c,
-                    None => continue,
-                };
-                let scope_name = scope_os.to_string_lossy().to_string();
-                let rest: PathBuf = it.collect();
-
-                // Descobre destino real
-                let out_path_opt = if let Some(base) = config.path_map.get(&scope_name) {
-                    Some(base.join(&rest))
-                } else if let Some(root_base) = config.path_map.get("Root") {
-                    // Fallback para raiz antiga
-                    Some(root_base.join(&rel))
-                } else {
-                    None
-                };
+/// Extrai as camadas em ordem. Erros por entrada ficam no `report`; só o
+/// cancelamento interrompe.
+fn rebuild_layers(
+    store: &dyn SnapshotStore,
+    ids: &[String],
+    plan: &RestorePlan,
+    run: &mut Extraction,
+    tx: &EventSender,
+    report: &mut Report,
+) -> BlkResult<()> {
+    let total_layers = ids.len().max(1) as f32;
 
-                if let Some(out_path) = out_path_opt {
-                    if let Some(parent) = out_path.parent() {
-                        fs::create_dir_all(parent).ok();
-                    }
-                    // Copy overwrites because we are layering up
-                    let _ = fs::copy(path, &out_path);
-                }
-            }
-            let _ = fs::remove_dir_all(&extract_root);
+    for (idx, id) in ids.iter().enumerate() {
+        let entries = &plan.layers[idx];
+        let label = format!(
+            "Unpacking Layer {}/{} ({}, {} entries)",
+            idx + 1,
+            ids.len(),
+            id,
+            entries.len()
+        );
+        tx.send(EngineEvent::phase(10.0 + ((idx as f32 / total_layers) * 80.0), label)).ok();
+
+        if entries.is_empty() {
+            continue;
         }
 
-        // Apply manifest specific deletions (files deleted in this delta)
-        let manifest_path = app_root
-            .join(".blk")
-            .join("sets")
-            .join(format!("{id}.json"));
-        if let Ok(content) = fs::read_to_string(&manifest_path) {
-            if let Ok(man) = serde_json::from_str::<SetManifest>(&content) {
-                if !man.deleted_paths.is_empty() {
-                    for del_key in man.deleted_paths {
-                        if let Some((scope, rel)) = del_key.split_once("::") {
-                            if let Some(base) = config.path_map.get(scope) {
-                                let target = base.join(rel);
-                                if target.exists() {
-                                    let _ = fs::remove_file(target);
-                                }
-                            }
-                        }
-                    }
-                }
+        let extracted = store
+            .open(id)
+            .at("open snapshot", id)
+            .and_then(|archive| restore_zip_entries(archive, id, entries, run, report));
+        match extracted {
+            Err(BlkError::Cancelled) => return Err(BlkError::Cancelled),
+            other => {
+                report.check(other);
             }
         }
     }
-
-    tx.send((95.0, "Pruning empty directories...".into())).ok();
-    prune_empty_dirs(&config);
-
-    tx.send((99.0, "Regenerating baseline...".into())).ok();
-    let state = scan_state(&config, &scopes, &exclusions, None);
-    save_baseline(app_root, &state);
-    tx.send((100.0, "✅ Restore completed (Nuclear Clean)!".into())).ok();
+    Ok(())
 }
 
 // -----------------------------------------------------------------------------
 // Save new delta
 // -----------------------------------------------------------------------------
 
+#[allow(clippy::too_many_arguments)]
 pub fn engine_save_new_delta(
     app_root: &Path,
     config: BlkConfig,
@@ -705,25 +1578,33 @@ pub fn engine_save_new_delta(
     parent_id: Option<String>,
     scopes: Vec<String>,
     exclusions: Vec<String>,
-    tx: mpsc::Sender<(f32, String)>,
-) {
-    tx.send((0.0, "Analyzing changes...".into())).ok();
-
-    let baseline = load_baseline(app_root);
-    let id = name
+    cancel: CancelToken,
+    tx: EventSender,
+) -> BlkResult<Report> {
+    tx.send(EngineEvent::Started { operation: "save delta" }).ok();
+    tx.send(EngineEvent::phase(0.0, "Analyzing changes...")).ok();
+    let _lock = RepoLock::acquire(app_root, "save delta")?;
+    let config = resolve_path_map(&config, app_root)?;
+
+    let baseline = load_baseline(app_root)?;
+    // Fora de [a-z0-9._-] vira '_': o id é nome de arquivo em qualquer SO
+    let id: String = name
         .to_lowercase()
-        .replace(' ', "_")
-        .replace('/', "")
-        .replace('\\', "");
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | '0'..='9' | '.' | '-' => c,
+            _ => '_',
+        })
+        .collect();
 
     // Identifica arquivos deletados em relação ao baseline anterior
     let mut deleted_paths = Vec::new();
-    for (key, _) in &baseline {
+    for key in baseline.keys() {
         if let Some((scope, rel_path)) = key.split_once("::") {
             if scopes.iter().any(|s| s == scope) {
                 if let Some(root_path) = config.path_map.get(scope) {
                     let real_path = root_path.join(rel_path);
-                    if !real_path.exists() {
+                    if fs::symlink_metadata(&real_path).is_err() {
                         deleted_paths.push(key.clone());
                     }
                 }
@@ -741,33 +1622,33 @@ pub fn engine_save_new_delta(
         deleted_paths: deleted_paths.clone(),
     };
 
-    let sets_dir = app_root.join(".blk").join("sets");
-    if let Ok(json) = serde_json::to_string_pretty(&manifest) {
-        if let Err(e) = fs::write(sets_dir.join(format!("{id}.json")), json) {
-            tx.send((100.0, format!("Error writing JSON: {e}"))).ok();
-            return;
-        }
+    // O id vem do nome: confere antes de gravar a camada, senão "Vanilla" sobre
+    // o vanilla sobrescreveria o zip de outro set
+    let store = open_store(app_root, &config)?;
+    if store.contains(&id) || manifest_path(app_root, &id).exists() {
+        return Err(BlkError::Config(format!(
+            "A set with ID '{id}' already exists; pick another name"
+        )));
     }
+    validate_set_ids("set name", &manifest)?;
 
-    let staging_root = app_root.join(".blk").join("staging").join(&id);
-    if staging_root.exists() {
-        let _ = fs::remove_dir_all(&staging_root);
-    }
-    fs::create_dir_all(&staging_root).ok();
-
-    let mut files_included = 0;
+    let mut report = Report::new("");
+    let mut entries = Vec::new();
 
     for scope_name in &scopes {
         if let Some(root) = config.path_map.get(scope_name) {
             if !root.exists() {
                 continue;
             }
-            let scope_dest = staging_root.join(scope_name);
 
             for entry in WalkDir::new(root) {
+                cancel.check()?;
                 let entry = match entry {
                     Ok(e) => e,
-                    Err(_) => continue,
+                    Err(e) => {
+                        report.fail(walk_error(e));
+                        continue;
+                    }
                 };
                 let path = entry.path();
 
@@ -778,76 +1659,72 @@ pub fn engine_save_new_delta(
                     continue;
                 }
 
-                if path.is_file() {
-                    let rel_key = path
-                        .strip_prefix(root)
-                        .unwrap_or(path)
-                        .to_string_lossy()
-                        .replace("\\", "/");
-                    let key = format!("{}::{}", scope_name, rel_key);
-
-                    let current_hash = hash_file(path);
-
-                    let is_modified = match baseline.get(&key) {
-                        Some(old_entry) => old_entry.hash != current_hash,
-                        None => true,
-                    };
-
-                    // Salva se mudou ou é novo
-                    if is_modified {
-                        if let Ok(rel) = path.strip_prefix(root) {
-                            let dest = scope_dest.join(rel);
-                            if let Some(parent) = dest.parent() {
-                                fs::create_dir_all(parent).ok();
-                            }
-                            let _ = fs::copy(path, dest);
-                            files_included += 1;
-                        }
-                    }
+                let rel_key = path
+                    .strip_prefix(root)
+                    .unwrap_or(path)
+                    .to_string_lossy()
+                    .replace("\\", "/");
+                if rel_key.is_empty() {
+                    continue;
+                }
+                let key = format!("{}::{}", scope_name, rel_key);
+
+                let current = match read_entry(path, None) {
+                    Some(e) => e,
+                    None => continue,
+                };
+
+                let is_modified = match baseline.get(&key) {
+                    Some(old_entry) => entry_changed(old_entry, &current),
+                    None => true,
+                };
+
+                // Salva se mudou ou é novo (arquivos, pastas e symlinks)
+                if is_modified {
+                    entries.push(ArchiveEntry {
+                        name: format!("{}/{}", scope_name, rel_key),
+                        source: path.to_path_buf(),
+                    });
                 }
             }
         }
     }
 
-    tx.send((
+    tx.send(EngineEvent::phase(
         40.0,
-        format!("Compressing {} files...", files_included),
+        format!("Compressing {} files...", entries.len()),
     ))
     .ok();
 
-    let archive = app_root
-        .join(".blk")
-        .join("snapshots")
-        .join(format!("{id}.zip"));
-    if let Err(e) = create_zip_archive(&archive, &staging_root) {
-        tx.send((100.0, format!("Compression error: {e}"))).ok();
-        return;
-    }
+    // Camada primeiro, manifesto depois: um set nunca aponta para zip inexistente.
+    // Depois da camada gravada não há mais ponto de cancelamento.
+    store_zip_archive(store.as_ref(), &id, &entries, &config.compression, &cancel, &tx)?;
 
-    let _ = fs::remove_dir_all(&staging_root);
+    save_manifest(app_root, &manifest)?;
 
-    tx.send((90.0, "Updating baseline...".into())).ok();
-    let state = scan_state(&config, &scopes, &exclusions, Some(&baseline));
-    save_baseline(app_root, &state);
+    tx.send(EngineEvent::phase(90.0, "Updating baseline...")).ok();
+    let state = scan_state(&config, &scopes, &exclusions, Some(&baseline), &mut report.failures);
+    save_baseline(app_root, &state)?;
 
-    tx.send((
-        100.0,
-        format!(
-            "Saved: {id} (+{}, -{})",
-            files_included,
-            deleted_paths.len()
-        ),
-    ))
-    .ok();
+    report.summary = format!(
+        "Saved: {id} (+{}, -{})",
+        entries.len(),
+        deleted_paths.len()
+    );
+    Ok(report)
 }
 
 pub fn engine_delete_cascade(
     app_root: &Path,
+    config: BlkConfig,
     target_id: String,
     all_manifests: &Vec<SetManifest>,
-    tx: mpsc::Sender<(f32, String)>,
-) {
-    tx.send((0.0, "Mapping cascade delete...".into())).ok();
+    tx: EventSender,
+) -> BlkResult<Report> {
+    tx.send(EngineEvent::Started { operation: "delete" }).ok();
+    tx.send(EngineEvent::phase(0.0, "Mapping cascade delete...")).ok();
+    let _lock = RepoLock::acquire(app_root, "delete")?;
+    let store = open_store(app_root, &config)?;
     let mut to_delete = Vec::new();
     let mut queue = vec![target_id.clone()];
     let mut idx = 0;
@@ -864,25 +1741,512 @@ pub fn engine_delete_cascade(
         idx += 1;
     }
     let total = to_delete.len().max(1) as f32;
+    let mut report = Report::new(format!("{} sets deleted.", total));
     for (i, id) in to_delete.iter().enumerate() {
         let label = format!("Deleting {}/{} ({id})", i + 1, to_delete.len());
         let pct = (i as f32 / total) * 100.0;
-        tx.send((pct, label)).ok();
-        let json = app_root
-            .join(".blk")
-            .join("sets")
-            .join(format!("{id}.json"));
+        tx.send(EngineEvent::phase(pct, label)).ok();
+        let json = manifest_path(app_root, id);
         if json.exists() {
-            let _ = fs::remove_file(json);
-        }
-        let archive_zip = app_root
-            .join(".blk")
-            .join("snapshots")
-            .join(format!("{id}.zip"));
-        if archive_zip.exists() {
-            let _ = fs::remove_file(archive_zip);
+            report.check(fs::remove_file(&json).at("remove", &json));
         }
+        report.check(store.remove(id).at("remove snapshot", id));
         thread::sleep(Duration::from_millis(50));
     }
-    tx.send((100.0, format!("✅ {} sets deleted.", total))).ok();
-}
\ No newline at end of file
+    Ok(report)
+}
+
+// -----------------------------------------------------------------------------
+// Verify
+// -----------------------------------------------------------------------------
+
+/// Relê todas as camadas até o fim (o zip confere o CRC de cada entrada) e
+/// confere a linhagem. Só leitura: não segura o lock nem altera nada.
+pub fn engine_verify(
+    app_root: &Path,
+    config: BlkConfig,
+    sets: &[SetManifest],
+    cancel: CancelToken,
+    tx: EventSender,
+) -> BlkResult<Report> {
+    tx.send(EngineEvent::Started { operation: "verify" }).ok();
+    let store = open_store(app_root, &config)?;
+    let total_bytes = sets.iter().filter_map(|m| store.size(&m.id)).sum();
+    let mut counter = FileCounter::new(&tx, sets.len(), total_bytes);
+    let mut report = Report::new(format!("Verified {} set(s)", sets.len()));
+
+    for (i, man) in sets.iter().enumerate() {
+        cancel.check()?;
+        let pct = (i as f32 / sets.len().max(1) as f32) * 100.0;
+        tx.send(EngineEvent::phase(pct, format!("Verifying {} ({})", man.id, man.name))).ok();
+        counter.advance(store.size(&man.id).unwrap_or(0));
+
+        if let Some(parent) = &man.parent_id {
+            if !sets.iter().any(|m| &m.id == parent) {
+                report.fail(BlkError::Integrity(format!(
+                    "{}: parent '{parent}' is missing",
+                    man.id
+                )));
+            }
+        }
+        if !store.contains(&man.id) {
+            report.fail(BlkError::Integrity(format!("{}: snapshot layer is missing", man.id)));
+            continue;
+        }
+
+        let read_all = store
+            .open(&man.id)
+            .at("open snapshot", &man.id)
+            .and_then(|file| zip::ZipArchive::new(file).at("read snapshot", &man.id))
+            .and_then(|mut archive| {
+                for idx in 0..archive.len() {
+                    let mut entry = archive.by_index(idx).at("read snapshot", &man.id)?;
+                    let name = format!("{}:{}", man.id, entry.name());
+                    io::copy(&mut entry, &mut io::sink()).at("verify", name)?;
+                }
+                Ok(())
+            });
+        report.check(read_all);
+    }
+
+    if !report.failures.is_empty() {
+        report.summary = "Verification found problems".into();
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::load_config;
+    use crate::test_support::{init_repo, set_manifest, silent};
+    use std::io::Write;
+
+    fn restore(root: &Path, ids: &[&str], cancel: CancelToken) -> BlkResult<Report> {
+        engine_restore_chain(
+            root,
+            load_config(root).unwrap(),
+            ids.iter().map(|id| id.to_string()).collect(),
+            vec!["Root".into()],
+            vec![],
+            cancel,
+            silent(),
+        )
+    }
+
+    // Camada montada à mão, como chegaria num bundle de terceiros
+    fn put_layer(
+        root: &Path,
+        id: &str,
+        parent: &str,
+        build: impl FnOnce(&mut zip::ZipWriter<Box<dyn crate::storage::SnapshotWriter>>),
+    ) {
+        let store = open_store(root, &load_config(root).unwrap()).unwrap();
+        let mut zip = zip::ZipWriter::new(store.create(id).unwrap());
+        build(&mut zip);
+        zip.finish().unwrap().commit().unwrap();
+        save_manifest(root, &set_manifest(id, Some(parent))).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn restore_does_not_write_through_a_symlink_from_the_layer() {
+        let repo = init_repo(&[("game.txt", "base")]);
+        let outside = tempfile::tempdir().unwrap();
+        let target = outside.path().to_string_lossy().to_string();
+        put_layer(repo.path(), "evil", "vanilla", |zip| {
+            let options = FullFileOptions::default();
+            zip.add_symlink("Root/link", target, options.clone()).unwrap();
+            zip.start_file("Root/link/escaped.txt", options).unwrap();
+            zip.write_all(b"pwned").unwrap();
+        });
+
+        let report = restore(repo.path(), &["vanilla", "evil"], CancelToken::default()).unwrap();
+
+        assert!(!outside.path().join("escaped.txt").exists());
+        assert_eq!(fs::read_to_string(repo.path().join("game.txt")).unwrap(), "base");
+        // A entrada recusada conta como falha, e com falha a lixeira fica
+        assert_eq!(report.failures.len(), 2, "{:?}", report.failures);
+        let kept: Vec<PathBuf> = fs::read_dir(repo.path())
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| p.file_name().unwrap().to_string_lossy().starts_with(TRASH_PREFIX))
+            .collect();
+        assert_eq!(kept.len(), 1);
+        assert_eq!(fs::read_to_string(kept[0].join("game.txt")).unwrap(), "base");
+    }
+
+    #[test]
+    fn restore_with_a_missing_layer_leaves_the_scopes_alone() {
+        let repo = init_repo(&[("game.txt", "base"), ("mods/new.txt", "mine")]);
+        put_layer(repo.path(), "patch", "vanilla", |_| {});
+        fs::remove_file(repo.path().join(".blk/snapshots/vanilla.zip")).unwrap();
+
+        let err = restore(repo.path(), &["vanilla", "patch"], CancelToken::default()).unwrap_err();
+
+        assert!(matches!(err, BlkError::NotFound(_)), "{err}");
+        assert_eq!(fs::read_to_string(repo.path().join("game.txt")).unwrap(), "base");
+        assert_eq!(fs::read_to_string(repo.path().join("mods/new.txt")).unwrap(), "mine");
+    }
+
+    #[test]
+    fn wipe_roll_back_puts_files_and_empty_folders_back() {
+        let repo = init_repo(&[("game.txt", "base"), ("mods/a.txt", "a")]);
+        fs::create_dir(repo.path().join("my_empty_dir")).unwrap();
+        let config = resolve_path_map(&load_config(repo.path()).unwrap(), repo.path()).unwrap();
+        let scopes = vec!["Root".to_string()];
+        let mut trash = Trash::new();
+        let mut report = Report::new("");
+
+        let wiped = nuke_scopes(&config, &scopes, &[], &mut trash, &CancelToken::default(), &silent(), &mut report)
+            .unwrap();
+        assert_eq!(wiped, 2);
+        assert!(!repo.path().join("game.txt").exists());
+
+        // O que o cancelamento faz depois de uma extração parcial
+        fs::remove_dir(repo.path().join("my_empty_dir")).unwrap();
+        fs::write(repo.path().join("my_empty_dir"), "from the layer").unwrap();
+        trash.roll_back(&[repo.path().join("my_empty_dir")], &mut report);
+
+        assert!(report.failures.is_empty(), "{:?}", report.failures);
+        assert!(repo.path().join("my_empty_dir").is_dir());
+        assert_eq!(fs::read_to_string(repo.path().join("game.txt")).unwrap(), "base");
+        assert_eq!(fs::read_to_string(repo.path().join("mods/a.txt")).unwrap(), "a");
+        assert!(leftover_trash(&config, &scopes).is_empty());
+    }
+
+    #[test]
+    fn restore_prunes_only_folders_the_layers_do_not_bring() {
+        let repo = tempfile::tempdir().unwrap();
+        fs::create_dir(repo.path().join("empty_in_set")).unwrap();
+        fs::write(repo.path().join("game.txt"), "base").unwrap();
+        engine_auto_init(repo.path(), silent()).unwrap();
+        fs::create_dir_all(repo.path().join("stray/nested")).unwrap();
+
+        restore(repo.path(), &["vanilla"], CancelToken::default()).unwrap();
+
+        assert!(repo.path().join("empty_in_set").is_dir());
+        assert!(!repo.path().join("stray").exists());
+        assert_eq!(fs::read_to_string(repo.path().join("game.txt")).unwrap(), "base");
+    }
+
+    #[test]
+    fn restore_puts_back_the_recorded_modification_times() {
+        let repo = tempfile::tempdir().unwrap();
+        fs::create_dir(repo.path().join("mods")).unwrap();
+        // Segundos ímpares: o DOS time do zip só guarda pares
+        let stamps = [("game.txt", 1_000_000_001), ("mods/a.txt", 1_234_567_891)];
+        for (rel, secs) in stamps {
+            let path = repo.path().join(rel);
+            fs::write(&path, rel).unwrap();
+            set_file_mtime(&path, secs).unwrap();
+        }
+        engine_auto_init(repo.path(), silent()).unwrap();
+        fs::write(repo.path().join("game.txt"), "changed").unwrap();
+        fs::remove_file(repo.path().join("mods/a.txt")).unwrap();
+
+        restore(repo.path(), &["vanilla"], CancelToken::default()).unwrap();
+
+        for (rel, secs) in stamps {
+            let meta = fs::metadata(repo.path().join(rel)).unwrap();
+            assert_eq!(mtime_secs(&meta), secs, "{rel}");
+        }
+        assert_eq!(fs::read_to_string(repo.path().join("game.txt")).unwrap(), "game.txt");
+    }
+
+    #[test]
+    fn scope_used_by_a_set_cannot_leave_the_path_map() {
+        let repo = init_repo(&[("game.txt", "base")]);
+        engine_update_global_path(repo.path(), "Mods".into(), "${ROOT}/mods".into()).unwrap();
+
+        let err = engine_remove_global_path(repo.path(), "Root").unwrap_err();
+        engine_remove_global_path(repo.path(), "Mods").unwrap();
+
+        assert!(matches!(err, BlkError::Config(_)), "{err}");
+        let config = load_config(repo.path()).unwrap();
+        assert!(config.path_map.contains_key("Root"));
+        assert!(!config.path_map.contains_key("Mods"));
+    }
+
+    #[test]
+    fn config_changes_wait_for_the_repo_lock() {
+        let repo = init_repo(&[("game.txt", "base")]);
+        let held = RepoLock::acquire(repo.path(), "save").unwrap();
+
+        let busy = [
+            engine_update_global_path(repo.path(), "Mods".into(), "${ROOT}/mods".into()),
+            engine_remove_global_path(repo.path(), "Root"),
+            engine_update_compression(repo.path(), CompressionProfile::Max),
+            engine_update_remote(repo.path(), repo.path().join("remote")),
+        ];
+        assert!(busy.iter().all(|r| matches!(r, Err(BlkError::Busy(_)))));
+        drop(held);
+
+        engine_update_global_path(repo.path(), "Mods".into(), "${ROOT}/mods".into()).unwrap();
+        assert!(load_config(repo.path()).unwrap().path_map.contains_key("Mods"));
+    }
+
+    fn save(root: &Path, name: &str, parent: &str) -> BlkResult<Report> {
+        engine_save_new_delta(
+            root,
+            load_config(root).unwrap(),
+            name.into(),
+            Some(parent.into()),
+            vec!["Root".into()],
+            vec![],
+            CancelToken::default(),
+            silent(),
+        )
+    }
+
+    // Arquivos da camada (pastas e symlinks ficam de fora) com o conteúdo
+    fn layer_files(root: &Path, id: &str) -> Vec<(String, String)> {
+        let store = open_store(root, &load_config(root).unwrap()).unwrap();
+        let mut archive = zip::ZipArchive::new(store.open(id).unwrap()).unwrap();
+        let mut files = Vec::new();
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i).unwrap();
+            if file.is_file() {
+                let mut body = String::new();
+                io::Read::read_to_string(&mut file, &mut body).unwrap();
+                files.push((file.name().to_string(), body));
+            }
+        }
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn save_streams_changed_files_without_a_staging_copy() {
+        let repo = init_repo(&[("game.txt", "base"), ("keep.txt", "same")]);
+        let mods = tempfile::tempdir().unwrap();
+        fs::write(mods.path().join("a.pak"), "mod").unwrap();
+        engine_update_global_path(repo.path(), "Mods".into(), mods.path().to_string_lossy().into()).unwrap();
+        fs::write(repo.path().join("game.txt"), "patched").unwrap();
+
+        engine_save_new_delta(
+            repo.path(),
+            load_config(repo.path()).unwrap(),
+            "patch".into(),
+            Some("vanilla".into()),
+            vec!["Root".into(), "Mods".into()],
+            vec![],
+            CancelToken::default(),
+            silent(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            layer_files(repo.path(), "patch"),
+            [("Mods/a.pak".to_string(), "mod".to_string()), ("Root/game.txt".into(), "patched".into())]
+        );
+        // Nada de cópia intermediária dentro da .blk, nem durante o init
+        for entry in WalkDir::new(repo.path().join(".blk")) {
+            let name = entry.unwrap().file_name().to_string_lossy().to_string();
+            assert!(!name.starts_with("staging") && name != "tmp_extract", "{name}");
+        }
+    }
+
+    #[test]
+    fn restore_streams_each_entry_to_its_own_scope() {
+        let repo = init_repo(&[("game.txt", "base")]);
+        let mods = tempfile::tempdir().unwrap();
+        fs::create_dir(mods.path().join("sub")).unwrap();
+        fs::write(mods.path().join("sub/a.pak"), "mod").unwrap();
+        engine_update_global_path(repo.path(), "Mods".into(), mods.path().to_string_lossy().into()).unwrap();
+        let scopes = vec!["Root".to_string(), "Mods".to_string()];
+        fs::write(repo.path().join("game.txt"), "patched").unwrap();
+        let config = load_config(repo.path()).unwrap();
+        engine_save_new_delta(
+            repo.path(),
+            config.clone(),
+            "patch".into(),
+            Some("vanilla".into()),
+            scopes.clone(),
+            vec![],
+            CancelToken::default(),
+            silent(),
+        )
+        .unwrap();
+        fs::remove_dir_all(mods.path().join("sub")).unwrap();
+        fs::write(repo.path().join("game.txt"), "broken").unwrap();
+        fs::write(repo.path().join("Mods"), "not the scope").unwrap();
+
+        engine_restore_chain(
+            repo.path(),
+            config,
+            vec!["vanilla".into(), "patch".into()],
+            scopes,
+            vec![],
+            CancelToken::default(),
+            silent(),
+        )
+        .unwrap();
+
+        // "Mods/..." da camada vai para a pasta do scope Mods, não para ${ROOT}/Mods
+        assert_eq!(fs::read_to_string(mods.path().join("sub/a.pak")).unwrap(), "mod");
+        assert_eq!(fs::read_to_string(repo.path().join("game.txt")).unwrap(), "patched");
+        assert!(!repo.path().join("Mods").exists());
+        assert!(!repo.path().join(".blk/tmp_extract").exists());
+    }
+
+    #[test]
+    fn restore_takes_each_path_once_from_the_topmost_layer() {
+        let repo = init_repo(&[("game.txt", "base"), ("video.ini", "v0"), ("old.txt", "old")]);
+        let mut parent = "vanilla".to_string();
+        for n in 1..=3 {
+            let id = format!("l{n}");
+            put_layer(repo.path(), &id, &parent, |zip| {
+                zip.start_file("Root/video.ini", FullFileOptions::default()).unwrap();
+                zip.write_all(format!("v{n}").as_bytes()).unwrap();
+            });
+            parent = id;
+        }
+        let mut l2 = set_manifest("l2", Some("l1"));
+        l2.deleted_paths = vec!["Root::old.txt".into()];
+        save_manifest(repo.path(), &l2).unwrap();
+        let ids: Vec<String> = ["vanilla", "l1", "l2", "l3"].iter().map(|s| s.to_string()).collect();
+        let config = resolve_path_map(&load_config(repo.path()).unwrap(), repo.path()).unwrap();
+        let store = open_store(repo.path(), &config).unwrap();
+
+        let plan = plan_restore(repo.path(), store.as_ref(), &config, &ids).unwrap();
+
+        let names = |layer: usize| -> Vec<String> {
+            plan.layers[layer]
+                .iter()
+                .map(|(_, p)| p.file_name().unwrap().to_string_lossy().to_string())
+                .collect()
+        };
+        assert_eq!(names(0), ["game.txt"]);
+        assert!(names(1).is_empty() && names(2).is_empty());
+        assert_eq!(names(3), ["video.ini"]);
+        assert_eq!(plan.deleted, [repo.path().join("old.txt")]);
+
+        let report = restore(repo.path(), &["vanilla", "l1", "l2", "l3"], CancelToken::default()).unwrap();
+        assert!(report.failures.is_empty(), "{:?}", report.failures);
+        assert_eq!(fs::read_to_string(repo.path().join("video.ini")).unwrap(), "v3");
+        assert!(!repo.path().join("old.txt").exists());
+    }
+
+    #[test]
+    fn compression_follows_codec_profile_and_extension_overrides() {
+        use zip::CompressionMethod::{Deflated, Stored, Zstd};
+        let mut cfg = CompressionConfig::default();
+        assert_eq!(compression_for(&cfg, "Root/data.txt"), (Zstd, Some(3)));
+        assert_eq!(compression_for(&cfg, "Root/music.OGG"), (Stored, None));
+
+        cfg.profile = CompressionProfile::Max;
+        assert_eq!(compression_for(&cfg, "a.txt"), (Zstd, Some(19)));
+        cfg.codec = Codec::Deflate;
+        assert_eq!(compression_for(&cfg, "a.txt"), (Deflated, Some(9)));
+        // Nível explícito vence o profile, dentro da faixa do codec
+        cfg.level = Some(40);
+        assert_eq!(compression_for(&cfg, "a.txt"), (Deflated, Some(9)));
+        cfg.overrides.insert("txt".into(), Codec::Store);
+        assert_eq!(compression_for(&cfg, "a.txt"), (Stored, None));
+    }
+
+    #[test]
+    fn save_stores_already_compressed_files_as_is() {
+        let repo = init_repo(&[("game.txt", "base")]);
+        fs::write(repo.path().join("track.ogg"), "ogg data").unwrap();
+        fs::write(repo.path().join("notes.txt"), "text").unwrap();
+        save(repo.path(), "sounds", "vanilla").unwrap();
+
+        let store = open_store(repo.path(), &load_config(repo.path()).unwrap()).unwrap();
+        let mut archive = zip::ZipArchive::new(store.open("sounds").unwrap()).unwrap();
+        let method = |archive: &mut zip::ZipArchive<_>, name| archive.by_name(name).unwrap().compression();
+        assert_eq!(method(&mut archive, "Root/track.ogg"), zip::CompressionMethod::Stored);
+        assert_eq!(method(&mut archive, "Root/notes.txt"), zip::CompressionMethod::Zstd);
+    }
+
+    #[test]
+    fn parallel_compression_keeps_the_entry_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut entries = Vec::new();
+        for i in 0..40 {
+            let source = dir.path().join(format!("f{i}.txt"));
+            fs::write(&source, format!("file {i} ").repeat(i * 50)).unwrap();
+            entries.push(ArchiveEntry { name: format!("Root/f{i}.txt"), source });
+        }
+        // Acima do limite: vai em streaming, no meio dos que vêm do pool
+        let big = dir.path().join("big.bin");
+        File::create(&big).unwrap().set_len(PARALLEL_MAX_BYTES + 1).unwrap();
+        entries.insert(7, ArchiveEntry { name: "Root/big.bin".into(), source: big });
+        let compression = CompressionConfig::default();
+
+        let out = create_zip_archive(
+            io::Cursor::new(Vec::new()),
+            "test",
+            &entries,
+            &compression,
+            &CancelToken::default(),
+            &silent(),
+        )
+        .unwrap();
+
+        let mut archive = zip::ZipArchive::new(out).unwrap();
+        assert_eq!(archive.len(), entries.len());
+        for (i, entry) in entries.iter().enumerate() {
+            let mut file = archive.by_index(i).unwrap();
+            assert_eq!(file.name(), entry.name);
+            let mut body = Vec::new();
+            io::Read::read_to_end(&mut file, &mut body).unwrap();
+            assert_eq!(body, fs::read(&entry.source).unwrap(), "{}", entry.name);
+        }
+
+        let cancel = CancelToken::default();
+        cancel.cancel();
+        let cancelled = create_zip_archive(io::Cursor::new(Vec::new()), "test", &entries, &compression, &cancel, &silent());
+        assert!(matches!(cancelled, Err(BlkError::Cancelled)));
+    }
+
+    #[test]
+    fn outcome_ends_in_exactly_one_finished_or_error() {
+        let repo = init_repo(&[]);
+        let (tx, rx) = mpsc::channel();
+
+        send_outcome(repo.path(), &tx, Err(BlkError::Integrity("bad".into())));
+        let events: Vec<EngineEvent> = rx.try_iter().collect();
+        assert!(matches!(events[..], [EngineEvent::Error(BlkError::Integrity(_))]), "{events:?}");
+
+        let mut report = Report::new("Done");
+        report.fail(BlkError::Config("one".into()));
+        report.fail(BlkError::Config("two".into()));
+        send_outcome(repo.path(), &tx, Ok(report));
+        let events: Vec<EngineEvent> = rx.try_iter().collect();
+        match &events[..] {
+            [EngineEvent::Warning(log), EngineEvent::Finished { summary }] => {
+                assert!(log.contains("last_report.log"), "{log}");
+                assert_eq!(summary.failures.len(), 2);
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+        assert!(repo.path().join(".blk/last_report.log").is_file());
+    }
+
+    #[test]
+    fn save_refuses_a_name_whose_id_is_taken() {
+        let repo = init_repo(&[("game.txt", "base")]);
+        let layer = repo.path().join(".blk/snapshots/vanilla.zip");
+        let before = fs::read(&layer).unwrap();
+        fs::write(repo.path().join("mod.txt"), "new").unwrap();
+
+        let err = save(repo.path(), "Vanilla", "vanilla").unwrap_err();
+
+        assert!(matches!(err, BlkError::Config(_)), "{err}");
+        assert_eq!(fs::read(&layer).unwrap(), before);
+        assert_eq!(save(repo.path(), "My Mods", "vanilla").unwrap().summary, "Saved: my_mods (+1, -0)");
+        assert!(matches!(save(repo.path(), "my mods", "vanilla"), Err(BlkError::Config(_))));
+    }
+
+    #[test]
+    fn save_turns_the_name_into_a_portable_id() {
+        let repo = init_repo(&[("game.txt", "base")]);
+        fs::write(repo.path().join("mod.txt"), "new").unwrap();
+
+        assert!(matches!(save(repo.path(), "Con", "vanilla"), Err(BlkError::Integrity(_))));
+        assert!(!repo.path().join(".blk/snapshots/con.zip").exists());
+        assert_eq!(save(repo.path(), "Mods: v2/Café", "vanilla").unwrap().summary, "Saved: mods__v2_caf_ (+1, -0)");
+    }
+}
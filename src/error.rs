@@ -0,0 +1,161 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// -----------------------------------------------------------------------------
+// Erros do engine
+// -----------------------------------------------------------------------------
+
+#[derive(Debug)]
+pub enum BlkError {
+    /// Falha de sistema de arquivos, com a operação e o caminho envolvidos.
+    Io {
+        op: &'static str,
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// Zip ilegível ou que não pôde ser gravado.
+    Zip {
+        op: &'static str,
+        path: PathBuf,
+        source: zip::result::ZipError,
+    },
+    /// JSON de metadados (.blk) inválido.
+    Json {
+        op: &'static str,
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    /// Set, camada ou repositório que deveria existir e não existe.
+    NotFound(String),
+    /// Outra operação segura o lock do repositório.
+    Busy(String),
+    /// config.json, path_map ou variável de caminho inválidos.
+    Config(String),
+    /// Conteúdo que não bate com o hash ou a versão esperados.
+    Integrity(String),
+    /// Operação interrompida antes do fim (worker parou...).
+    Aborted(String),
+    /// O usuário pediu para parar; o que foi feito até ali já foi desfeito.
+    Cancelled,
+}
+
+pub type BlkResult<T> = Result<T, BlkError>;
+
+impl fmt::Display for BlkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlkError::Io { op, path, source } => {
+                write!(f, "Failed to {op} {}: {source}", path.to_string_lossy())
+            }
+            BlkError::Zip { op, path, source } => {
+                write!(f, "Failed to {op} {}: {source}", path.to_string_lossy())
+            }
+            BlkError::Json { op, path, source } => {
+                write!(f, "Failed to {op} {}: {source}", path.to_string_lossy())
+            }
+            BlkError::NotFound(msg)
+            | BlkError::Busy(msg)
+            | BlkError::Config(msg)
+            | BlkError::Integrity(msg)
+            | BlkError::Aborted(msg) => write!(f, "{msg}"),
+            BlkError::Cancelled => write!(f, "Operation cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for BlkError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BlkError::Io { source, .. } => Some(source),
+            BlkError::Zip { source, .. } => Some(source),
+            BlkError::Json { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Anexa operação e caminho a um erro de IO, zip ou JSON.
+pub trait Context<T> {
+    fn at(self, op: &'static str, path: impl AsRef<Path>) -> BlkResult<T>;
+}
+
+impl<T> Context<T> for io::Result<T> {
+    fn at(self, op: &'static str, path: impl AsRef<Path>) -> BlkResult<T> {
+        self.map_err(|source| BlkError::Io {
+            op,
+            path: path.as_ref().to_path_buf(),
+            source,
+        })
+    }
+}
+
+impl<T> Context<T> for zip::result::ZipResult<T> {
+    fn at(self, op: &'static str, path: impl AsRef<Path>) -> BlkResult<T> {
+        self.map_err(|source| BlkError::Zip {
+            op,
+            path: path.as_ref().to_path_buf(),
+            source,
+        })
+    }
+}
+
+impl<T> Context<T> for serde_json::Result<T> {
+    fn at(self, op: &'static str, path: impl AsRef<Path>) -> BlkResult<T> {
+        self.map_err(|source| BlkError::Json {
+            op,
+            path: path.as_ref().to_path_buf(),
+            source,
+        })
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Relatório de operações
+// -----------------------------------------------------------------------------
+
+/// Fim de uma operação que foi até o final, mas talvez com falhas por arquivo.
+#[derive(Debug)]
+pub struct Report {
+    pub summary: String,
+    pub failures: Vec<BlkError>,
+}
+
+impl Report {
+    pub fn new(summary: impl Into<String>) -> Self {
+        Report {
+            summary: summary.into(),
+            failures: Vec::new(),
+        }
+    }
+
+    pub fn fail(&mut self, err: BlkError) {
+        self.failures.push(err);
+    }
+
+    /// Guarda a falha de um passo que não interrompe a operação.
+    pub fn check<T>(&mut self, result: BlkResult<T>) -> Option<T> {
+        match result {
+            Ok(v) => Some(v),
+            Err(e) => {
+                self.fail(e);
+                None
+            }
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.failures.as_slice() {
+            [] => write!(f, "✅ {}", self.summary),
+            [only] => write!(f, "⚠ {} | 1 failure: {only}", self.summary),
+            [first, rest @ ..] => write!(
+                f,
+                "⚠ {} | {} failures, first: {first}",
+                self.summary,
+                rest.len() + 1
+            ),
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+
+use crate::error::{BlkError, BlkResult, Report};
+
+// -----------------------------------------------------------------------------
+// Eventos do engine
+// -----------------------------------------------------------------------------
+//
+// Toda operação em background fala com a TUI só por aqui. Uma operação
+// sempre termina com exatamente um `Finished` ou um `Error`.
+
+/// Avanço por arquivo dentro da fase atual.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileProgress {
+    pub bytes: u64,
+    pub total_bytes: u64,
+    pub files: usize,
+    pub total_files: usize,
+}
+
+#[derive(Debug)]
+pub enum EngineEvent {
+    Started { operation: &'static str },
+    /// Etapa com o percentual total da operação.
+    Phase { percent: f32, label: String },
+    FileProgress(FileProgress),
+    /// Algo que merece atenção mas não impede o resultado (lixeira de um restore interrompido...).
+    Warning(String),
+    Error(BlkError),
+    Finished { summary: Report },
+}
+
+pub type EventSender = mpsc::Sender<EngineEvent>;
+
+impl EngineEvent {
+    pub fn phase(percent: f32, label: impl Into<String>) -> Self {
+        EngineEvent::Phase {
+            percent,
+            label: label.into(),
+        }
+    }
+}
+
+/// Intervalo mínimo entre dois `FileProgress` (milhares de arquivos pequenos
+/// não podem inundar o canal).
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Conta arquivos/bytes processados e avisa a TUI de tempos em tempos.
+pub(crate) struct FileCounter<'a> {
+    tx: &'a EventSender,
+    progress: FileProgress,
+    last_sent: Instant,
+}
+
+impl<'a> FileCounter<'a> {
+    pub fn new(tx: &'a EventSender, total_files: usize, total_bytes: u64) -> Self {
+        let progress = FileProgress {
+            total_files,
+            total_bytes,
+            ..FileProgress::default()
+        };
+        tx.send(EngineEvent::FileProgress(progress)).ok();
+        FileCounter {
+            tx,
+            progress,
+            last_sent: Instant::now(),
+        }
+    }
+
+    pub fn advance(&mut self, bytes: u64) {
+        self.progress.files += 1;
+        self.progress.bytes += bytes;
+        let last = self.progress.files >= self.progress.total_files;
+        if last || self.last_sent.elapsed() >= PROGRESS_INTERVAL {
+            self.tx.send(EngineEvent::FileProgress(self.progress)).ok();
+            self.last_sent = Instant::now();
+        }
+    }
+}
+
+/// Pedido de cancelamento, compartilhado entre a TUI e a thread da operação.
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Ponto de parada entre um arquivo e outro.
+    pub fn check(&self) -> BlkResult<()> {
+        if self.0.load(Ordering::Relaxed) {
+            Err(BlkError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(rx: &mpsc::Receiver<EngineEvent>) -> Vec<(usize, u64)> {
+        rx.try_iter()
+            .map(|event| match event {
+                EngineEvent::FileProgress(p) => (p.files, p.bytes),
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn file_counter_throttles_but_always_reports_the_last_file() {
+        let (tx, rx) = mpsc::channel();
+        let mut counter = FileCounter::new(&tx, 1000, 10_000);
+        for _ in 0..1000 {
+            counter.advance(10);
+        }
+
+        let sent = progress(&rx);
+        assert_eq!(sent.first(), Some(&(0, 0)));
+        assert_eq!(sent.last(), Some(&(1000, 10_000)));
+        assert!(sent.len() < 100, "{} events", sent.len());
+    }
+
+    #[test]
+    fn cancel_is_seen_by_every_clone() {
+        let token = CancelToken::default();
+        let worker = token.clone();
+        assert!(worker.check().is_ok());
+        token.cancel();
+        assert!(matches!(worker.check(), Err(BlkError::Cancelled)));
+    }
+}
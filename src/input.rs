@@ -0,0 +1,237 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{layout::Rect, widgets::Paragraph, Frame};
+use unicode_width::UnicodeWidthStr;
+
+// -----------------------------------------------------------------------------
+// Campo de texto da TUI
+// -----------------------------------------------------------------------------
+//
+// Um só editor para todos os campos (nome do set, caminhos, confirmações).
+// O cursor é um índice em bytes sempre numa fronteira de caractere; a coluna
+// na tela vem da largura Unicode do texto antes dele.
+
+#[derive(Default)]
+pub struct TextInput {
+    text: String,
+    cursor: usize,
+}
+
+// Palavra para Ctrl+setas e Ctrl+W: separadores de caminho também param
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+impl TextInput {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Troca o conteúdo e põe o cursor no fim.
+    pub fn set(&mut self, text: impl Into<String>) {
+        self.text = text.into();
+        self.cursor = self.text.len();
+    }
+
+    pub fn clear(&mut self) {
+        self.set(String::new());
+    }
+
+    /// Texto colado (bracketed paste); quebras de linha viram espaço.
+    pub fn paste(&mut self, pasted: &str) {
+        let clean: String = pasted
+            .trim_end_matches(['\r', '\n'])
+            .chars()
+            .map(|c| if c == '\r' || c == '\n' { ' ' } else { c })
+            .filter(|c| !c.is_control())
+            .collect();
+        self.text.insert_str(self.cursor, &clean);
+        self.cursor += clean.len();
+    }
+
+    fn prev_boundary(&self, from: usize) -> usize {
+        self.text[..from]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    fn next_boundary(&self, from: usize) -> usize {
+        self.text[from..]
+            .chars()
+            .next()
+            .map_or(from, |c| from + c.len_utf8())
+    }
+
+    fn word_left(&self) -> usize {
+        let mut pos = self.cursor;
+        let mut chars = self.text[..self.cursor].char_indices().rev().peekable();
+        while let Some((i, _)) = chars.next_if(|(_, c)| !is_word_char(*c)) {
+            pos = i;
+        }
+        while let Some((i, _)) = chars.next_if(|(_, c)| is_word_char(*c)) {
+            pos = i;
+        }
+        pos
+    }
+
+    fn word_right(&self) -> usize {
+        let mut chars = self.text[self.cursor..].char_indices().peekable();
+        while chars.next_if(|(_, c)| !is_word_char(*c)).is_some() {}
+        while chars.next_if(|(_, c)| is_word_char(*c)).is_some() {}
+        chars.peek().map_or(self.text.len(), |(i, _)| self.cursor + i)
+    }
+
+    /// Aplica uma tecla de edição. `true` se o texto mudou.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        let before = self.text.len();
+
+        match key.code {
+            // Ctrl+Alt é AltGr no Windows (\, @, {...): é texto
+            KeyCode::Char(c) if ctrl == alt => {
+                self.text.insert(self.cursor, c);
+                self.cursor += c.len_utf8();
+                return true;
+            }
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::Char('e') if ctrl => self.cursor = self.text.len(),
+            KeyCode::Char('b') if alt => self.cursor = self.word_left(),
+            KeyCode::Char('f') if alt => self.cursor = self.word_right(),
+            KeyCode::Char('u') if ctrl => {
+                self.text.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            KeyCode::Char('k') if ctrl => self.text.truncate(self.cursor),
+            KeyCode::Char('w') if ctrl => {
+                let start = self.word_left();
+                self.text.drain(start..self.cursor);
+                self.cursor = start;
+            }
+            KeyCode::Backspace if ctrl || alt => {
+                let start = self.word_left();
+                self.text.drain(start..self.cursor);
+                self.cursor = start;
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                let start = self.prev_boundary(self.cursor);
+                self.text.drain(start..self.cursor);
+                self.cursor = start;
+            }
+            KeyCode::Delete if ctrl || alt => {
+                let end = self.word_right();
+                self.text.drain(self.cursor..end);
+            }
+            KeyCode::Delete if self.cursor < self.text.len() => {
+                let end = self.next_boundary(self.cursor);
+                self.text.drain(self.cursor..end);
+            }
+            KeyCode::Left if ctrl || alt => self.cursor = self.word_left(),
+            KeyCode::Right if ctrl || alt => self.cursor = self.word_right(),
+            KeyCode::Left => self.cursor = self.prev_boundary(self.cursor),
+            KeyCode::Right => self.cursor = self.next_boundary(self.cursor),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.text.len(),
+            _ => {}
+        }
+        self.text.len() != before
+    }
+
+    /// Desenha numa linha e posiciona o cursor; o texto rola para a esquerda
+    /// quando o cursor passaria da borda.
+    pub fn render(&self, f: &mut Frame, area: Rect) {
+        if area.width == 0 || area.height == 0 {
+            return;
+        }
+        let column = self.text[..self.cursor].width().min(u16::MAX as usize) as u16;
+        let scroll = column.saturating_sub(area.width - 1);
+        f.render_widget(Paragraph::new(self.text.as_str()).scroll((0, scroll)), area);
+        f.set_cursor(area.x + column - scroll, area.y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    fn typed(text: &str) -> TextInput {
+        let mut input = TextInput::default();
+        for c in text.chars() {
+            input.handle_key(key(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+        input
+    }
+
+    fn press(input: &mut TextInput, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        input.handle_key(key(code, modifiers))
+    }
+
+    #[test]
+    fn editing_respects_multibyte_characters() {
+        let mut input = typed("ção");
+        press(&mut input, KeyCode::Left, KeyModifiers::NONE);
+        press(&mut input, KeyCode::Left, KeyModifiers::NONE);
+        assert!(press(&mut input, KeyCode::Backspace, KeyModifiers::NONE));
+        assert_eq!(input.text(), "ão");
+
+        press(&mut input, KeyCode::Char('é'), KeyModifiers::NONE);
+        assert!(press(&mut input, KeyCode::Delete, KeyModifiers::NONE));
+        assert_eq!(input.text(), "éo");
+    }
+
+    #[test]
+    fn word_moves_stop_at_path_separators() {
+        let mut input = typed("C:/Games/My_Mods/x");
+        press(&mut input, KeyCode::Left, KeyModifiers::CONTROL);
+        press(&mut input, KeyCode::Left, KeyModifiers::CONTROL);
+        assert!(press(&mut input, KeyCode::Char('w'), KeyModifiers::CONTROL));
+        assert_eq!(input.text(), "C:/My_Mods/x");
+
+        press(&mut input, KeyCode::Home, KeyModifiers::NONE);
+        press(&mut input, KeyCode::Right, KeyModifiers::ALT);
+        assert!(press(&mut input, KeyCode::Char('k'), KeyModifiers::CONTROL));
+        assert_eq!(input.text(), "C");
+    }
+
+    #[test]
+    fn cut_to_start_and_navigation_keys_report_changes() {
+        let mut input = typed("name");
+        assert!(!press(&mut input, KeyCode::Char('a'), KeyModifiers::CONTROL));
+        assert!(!press(&mut input, KeyCode::Backspace, KeyModifiers::NONE));
+        press(&mut input, KeyCode::End, KeyModifiers::NONE);
+        assert!(press(&mut input, KeyCode::Char('u'), KeyModifiers::CONTROL));
+        assert_eq!(input.text(), "");
+    }
+
+    #[test]
+    fn altgr_characters_are_text() {
+        let mut input = typed("a");
+        press(&mut input, KeyCode::Char('\\'), KeyModifiers::CONTROL | KeyModifiers::ALT);
+        assert_eq!(input.text(), "a\\");
+    }
+
+    #[test]
+    fn paste_goes_in_at_the_cursor_on_one_line() {
+        let mut input = typed("ab");
+        press(&mut input, KeyCode::Left, KeyModifiers::NONE);
+        input.paste("1\r\n2\t3\n");
+        assert_eq!(input.text(), "a1  23b");
+    }
+
+    #[test]
+    fn render_scrolls_to_keep_the_cursor_visible() {
+        let backend = ratatui::backend::TestBackend::new(10, 1);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        let input = typed("0123456789abcdef");
+
+        terminal.draw(|f| input.render(f, f.size())).unwrap();
+
+        assert_eq!(terminal.get_cursor().unwrap(), (9, 0));
+        let shown: String = terminal.backend().buffer().content().iter().map(|c| c.symbol()).collect();
+        assert_eq!(shown, "789abcdef ");
+    }
+}
@@ -0,0 +1,28 @@
+//! BLK: snapshots em camadas (delta) de diretórios inteiros.
+//!
+//! Tudo passa por [`Repository`]; os tipos de config e manifesto ficam em
+//! [`models`], os eventos de progresso em [`events`].
+
+mod bundle;
+mod engine;
+mod lock;
+mod meta;
+mod migrate;
+mod paths;
+mod repository;
+mod storage;
+mod sync;
+#[cfg(test)]
+mod test_support;
+
+pub mod error;
+pub mod events;
+pub mod models;
+
+pub use bundle::{bundle_scopes, read_bundle_manifest};
+pub use engine::{format_bytes, send_outcome};
+pub use error::{BlkError, BlkResult, Report};
+pub use events::{CancelToken, EngineEvent, EventSender, FileProgress};
+pub use migrate::FORMAT_VERSION;
+pub use paths::{complete_path, expand_path};
+pub use repository::Repository;
@@ -0,0 +1,295 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::engine::now_unix;
+use crate::error::{BlkError, BlkResult, Context};
+use crate::models::LockInfo;
+
+// -----------------------------------------------------------------------------
+// Lock do repositório
+// -----------------------------------------------------------------------------
+//
+// Toda operação que altera .blk, o baseline ou os scopes segura `.blk/lock`.
+// Duas instâncias (TUI + CLI, duas TUIs...) nunca escrevem ao mesmo tempo.
+
+/// Depois disso o lock é considerado abandonado mesmo com o PID vivo
+/// (o PID pode ter sido reaproveitado por outro processo).
+const LOCK_MAX_AGE_SECS: u64 = 12 * 60 * 60;
+
+fn lock_path(app_root: &Path) -> PathBuf {
+    app_root.join(".blk").join("lock")
+}
+
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    // Sinal 0 só testa se o processo existe; EPERM = existe, mas é de outro usuário
+    let res = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    res == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(windows)]
+fn process_alive(pid: u32) -> bool {
+    use windows_sys::Win32::Foundation::{
+        CloseHandle, GetLastError, ERROR_ACCESS_DENIED, STILL_ACTIVE,
+    };
+    use windows_sys::Win32::System::Threading::{
+        GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    let handle = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+    if handle.is_null() {
+        // Acesso negado = existe, mas é de outro usuário
+        return unsafe { GetLastError() } == ERROR_ACCESS_DENIED;
+    }
+    let mut code = 0u32;
+    let ok = unsafe { GetExitCodeProcess(handle, &mut code) } != 0;
+    unsafe { CloseHandle(handle) };
+    // Sem o código de saída, melhor supor vivo e deixar a idade decidir
+    !ok || code == STILL_ACTIVE as u32
+}
+
+#[cfg(not(any(unix, windows)))]
+fn process_alive(_pid: u32) -> bool {
+    // Sem como consultar aqui: fica só o critério de idade
+    true
+}
+
+#[cfg(unix)]
+fn host_name() -> String {
+    let mut buf = [0u8; 256];
+    let res = unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) };
+    if res != 0 {
+        return String::new();
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
+#[cfg(not(unix))]
+fn host_name() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_default()
+}
+
+fn is_stale(info: &LockInfo) -> bool {
+    if now_unix().saturating_sub(info.started_at) > LOCK_MAX_AGE_SECS {
+        return true;
+    }
+    // PID de outra máquina não diz nada aqui: só a idade vale. Lock sem host
+    // (versões antigas) conta como local.
+    let local = info.host.is_empty() || info.host.eq_ignore_ascii_case(&host_name());
+    local && !process_alive(info.pid)
+}
+
+fn describe(info: &LockInfo) -> String {
+    let age = now_unix().saturating_sub(info.started_at);
+    let since = if age < 60 {
+        format!("{age}s ago")
+    } else if age < 3600 {
+        format!("{} min ago", age / 60)
+    } else {
+        format!("{}h ago", age / 3600)
+    };
+    let place = match info.host.as_str() {
+        "" => format!("PID {}", info.pid),
+        host => format!("PID {} on {host}", info.pid),
+    };
+    format!(
+        "Repository busy: '{}' running in {place} (started {since})",
+        info.operation
+    )
+}
+
+fn recently_created(path: &Path) -> bool {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.elapsed().ok())
+        .is_some_and(|age| age.as_secs() < 10)
+}
+
+/// Tira `path` do caminho só se ele ainda contém `expected`. O rename para um
+/// nome único vem antes da leitura: se outro processo já trocou o lock por um
+/// novo, é esse novo que sai, a comparação falha e ele volta para o lugar.
+fn remove_if_unchanged(path: &Path, expected: &str) -> bool {
+    static SEQ: AtomicU64 = AtomicU64::new(0);
+    let aside = path.with_file_name(format!(
+        "lock.{}-{}",
+        std::process::id(),
+        SEQ.fetch_add(1, Ordering::Relaxed)
+    ));
+    if fs::rename(path, &aside).is_err() {
+        return false;
+    }
+    if fs::read_to_string(&aside).is_ok_and(|txt| txt == expected) {
+        let _ = fs::remove_file(&aside);
+        return true;
+    }
+    // hard_link não sobrescreve um lock que um terceiro tenha criado nesse meio-tempo
+    if fs::hard_link(&aside, path).is_ok() {
+        let _ = fs::remove_file(&aside);
+    } else if !path.exists() {
+        let _ = fs::rename(&aside, path);
+    }
+    false
+}
+
+/// Lock exclusivo; liberado quando sai de escopo.
+pub struct RepoLock {
+    path: PathBuf,
+    // Conteúdo gravado: o Drop só apaga o lock se ele ainda é este
+    json: String,
+}
+
+impl RepoLock {
+    pub fn acquire(app_root: &Path, operation: &str) -> BlkResult<RepoLock> {
+        let path = lock_path(app_root);
+        let info = LockInfo {
+            pid: std::process::id(),
+            operation: operation.to_string(),
+            started_at: now_unix(),
+            host: host_name(),
+        };
+
+        // Segunda volta só depois de remover um lock abandonado
+        for _ in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    let json = serde_json::to_string_pretty(&info).unwrap();
+                    if let Err(e) = file.write_all(json.as_bytes()).at("write", &path) {
+                        let _ = fs::remove_file(&path);
+                        return Err(e);
+                    }
+                    return Ok(RepoLock { path, json });
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    let txt = fs::read_to_string(&path).unwrap_or_default();
+                    let holder = serde_json::from_str::<LockInfo>(&txt).ok();
+                    match holder {
+                        Some(h) if !is_stale(&h) => return Err(BlkError::Busy(describe(&h))),
+                        // Ilegível e recente: outro processo pode estar no meio da escrita
+                        None if recently_created(&path) => {
+                            return Err(BlkError::Busy(
+                                "Repository busy: another operation is starting".into(),
+                            ))
+                        }
+                        // Dois processos podem julgar o mesmo lock abandonado:
+                        // só sai se ainda é o que foi lido
+                        _ => {
+                            remove_if_unchanged(&path, &txt);
+                        }
+                    }
+                }
+                Err(e) => return Err(e).at("create lock file", &path),
+            }
+        }
+
+        Err(BlkError::Busy("Repository busy: could not take the lock".into()))
+    }
+}
+
+impl Drop for RepoLock {
+    fn drop(&mut self) {
+        // Tomado como abandonado por outro processo: o lock de lá fica
+        remove_if_unchanged(&self.path, &self.json);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plant(root: &Path, pid: u32, host: &str, started_at: u64) {
+        let info = LockInfo {
+            pid,
+            operation: "save".into(),
+            started_at,
+            host: host.into(),
+        };
+        fs::write(lock_path(root), serde_json::to_string(&info).unwrap()).unwrap();
+    }
+
+    fn repo() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join(".blk")).unwrap();
+        dir
+    }
+
+    // PID de um processo que já terminou
+    fn dead_pid() -> u32 {
+        let mut child = std::process::Command::new(std::env::current_exe().unwrap())
+            .arg("--list")
+            .stdout(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+        child.wait().unwrap();
+        child.id()
+    }
+
+    #[test]
+    fn second_acquire_is_busy_until_the_first_is_dropped() {
+        let dir = repo();
+        let first = RepoLock::acquire(dir.path(), "save").unwrap();
+        assert!(matches!(RepoLock::acquire(dir.path(), "restore"), Err(BlkError::Busy(_))));
+        drop(first);
+        assert!(!lock_path(dir.path()).exists());
+        RepoLock::acquire(dir.path(), "restore").unwrap();
+    }
+
+    #[test]
+    fn lock_of_a_dead_local_process_is_taken_over() {
+        let dir = repo();
+        plant(dir.path(), dead_pid(), &host_name(), now_unix());
+        RepoLock::acquire(dir.path(), "save").unwrap();
+
+        // Lock gravado por versões sem o campo host
+        plant(dir.path(), dead_pid(), "", now_unix());
+        RepoLock::acquire(dir.path(), "save").unwrap();
+    }
+
+    #[test]
+    fn pid_of_another_host_is_not_checked_here() {
+        let dir = repo();
+        let other = format!("{}-elsewhere", host_name());
+        plant(dir.path(), dead_pid(), &other, now_unix());
+        assert!(matches!(RepoLock::acquire(dir.path(), "save"), Err(BlkError::Busy(_))));
+
+        // Só a idade libera o lock de outra máquina
+        plant(dir.path(), dead_pid(), &other, now_unix() - LOCK_MAX_AGE_SECS - 1);
+        RepoLock::acquire(dir.path(), "save").unwrap();
+    }
+
+    #[test]
+    fn live_local_process_keeps_the_lock() {
+        let dir = repo();
+        plant(dir.path(), std::process::id(), &host_name(), now_unix());
+        match RepoLock::acquire(dir.path(), "save") {
+            Err(BlkError::Busy(msg)) => assert!(msg.contains("'save'"), "{msg}"),
+            other => panic!("expected Busy, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn lock_replaced_after_the_stale_read_stays() {
+        let dir = repo();
+        let path = lock_path(dir.path());
+        plant(dir.path(), dead_pid(), &host_name(), now_unix());
+        let seen = fs::read_to_string(&path).unwrap();
+        // Outro processo já trocou o lock abandonado pelo seu
+        plant(dir.path(), std::process::id(), &host_name(), now_unix());
+        let live = fs::read_to_string(&path).unwrap();
+        assert!(!remove_if_unchanged(&path, &seen));
+        assert_eq!(fs::read_to_string(&path).unwrap(), live);
+        assert_eq!(fs::read_dir(dir.path().join(".blk")).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn drop_leaves_a_lock_that_is_no_longer_ours() {
+        let dir = repo();
+        let ours = RepoLock::acquire(dir.path(), "save").unwrap();
+        plant(dir.path(), std::process::id(), &host_name(), now_unix() - 5);
+        drop(ours);
+        assert!(lock_path(dir.path()).exists());
+    }
+}
@@ -1,17 +1,16 @@
-mod models;
-mod engine;
+mod input;
 
-use std::collections::HashMap;
-use std::fs;
+use std::collections::{BTreeMap, HashMap};
 use std::io;
 use std::panic;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use chrono::{Local, TimeZone};
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind},
+    event::{self, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, KeyEventKind},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -24,20 +23,29 @@ use ratatui::{
     Terminal,
 };
 
-use models::{BlkConfig, DiffSummary, SetManifest};
-use engine::{
-    engine_auto_init, engine_check_changes, engine_delete_cascade,
-    engine_restore_chain, engine_save_new_delta,
-    engine_update_global_path, engine_update_manifest,
-    format_bytes, get_snapshot_size,
+use blk::models::{BlkConfig, ContentEntry, DiffSummary, EntryKind, ScopeStatus, SetManifest};
+use blk::{
+    bundle_scopes, complete_path, expand_path, format_bytes, read_bundle_manifest, send_outcome, BlkError,
+    BlkResult, CancelToken, EngineEvent, FileProgress, Repository,
 };
 
+use crate::input::TextInput;
+
 enum InputMode {
     Normal,
     EditingName,
     ConfirmDelete,
     Configuring,
     AddingPath,
+    ExportPath,
+    ImportPath,
+    ImportScope,
+    RemotePath,
+    PathMap,
+    EditingScopePath,
+    ConfirmRemoveScope,
+    Searching,
+    Contents,
     Initializing,
 }
 
@@ -46,24 +54,180 @@ enum ConfigFocus {
     Exclusions,
 }
 
+/// O que foi digitado no AddingPath (aba de scopes).
+#[derive(Debug, PartialEq)]
+enum ScopeEntry {
+    /// Chave que já está no path_map.
+    Key(String),
+    /// Caminho novo (ou trocado) para `key`.
+    Path { key: String, path: String },
+}
+
+/// Uma linha do navegador de conteúdo: arquivo, ou pasta com o total de dentro.
+struct ContentRow {
+    name: String,
+    kind: EntryKind,
+    deleted: bool,
+    size: u64,
+    files: usize,
+    crc32: u32,
+    // Set que fornece a linha
+    source: String,
+    // Pasta com arquivos de mais de um set
+    mixed: bool,
+}
+
+impl ContentRow {
+    fn folder(name: &str) -> Self {
+        ContentRow {
+            name: name.to_string(),
+            kind: EntryKind::Dir,
+            deleted: true,
+            size: 0,
+            files: 0,
+            crc32: 0,
+            source: String::new(),
+            mixed: false,
+        }
+    }
+
+    // Pasta só aparece como apagada se tudo dentro dela foi apagado. A origem
+    // vem dos arquivos; as entradas de pasta só contam numa pasta vazia.
+    fn add(&mut self, entry: &ContentEntry) {
+        self.deleted &= entry.deleted;
+        if entry.deleted {
+            return;
+        }
+        if entry.kind == EntryKind::Dir {
+            if self.source.is_empty() {
+                self.source = entry.source.clone();
+            }
+            return;
+        }
+        if self.files == 0 {
+            self.source = entry.source.clone();
+        } else if self.source != entry.source {
+            self.mixed = true;
+        }
+        self.files += 1;
+        self.size += entry.size;
+    }
+}
+
+/// Filhos diretos de `dir` ("" é a raiz, com um item por scope). Pastas
+/// primeiro; apagados só entram na visão da camada.
+fn content_rows(entries: &[ContentEntry], dir: &str, full: bool) -> Vec<ContentRow> {
+    let prefix = if dir.is_empty() { String::new() } else { format!("{dir}/") };
+    let mut folders: BTreeMap<&str, ContentRow> = BTreeMap::new();
+    let mut files = Vec::new();
+
+    for entry in entries {
+        if full && entry.deleted {
+            continue;
+        }
+        let rest = match entry.path.strip_prefix(&prefix) {
+            Some(rest) => rest,
+            None => continue,
+        };
+        match rest.split_once('/') {
+            Some((name, _)) => folders
+                .entry(name)
+                .or_insert_with(|| ContentRow::folder(name))
+                .add(entry),
+            None if entry.kind == EntryKind::Dir => folders
+                .entry(rest)
+                .or_insert_with(|| ContentRow::folder(rest))
+                .add(entry),
+            None => files.push(ContentRow {
+                name: rest.to_string(),
+                kind: entry.kind,
+                deleted: entry.deleted,
+                size: entry.size,
+                files: 1,
+                crc32: entry.crc32,
+                source: entry.source.clone(),
+                mixed: false,
+            }),
+        }
+    }
+    folders.into_values().chain(files).collect()
+}
+
+// O zip só guarda o CRC32 de cada arquivo; a coluna diz isso em vez de
+// deixar o número passar por um hash de conteúdo
+fn contents_header(source_width: usize) -> String {
+    format!("{:>10}  {:<12}{:<source_width$}  Name", "Size", "CRC32", "Set")
+}
+
+// Autocompletar oferece caminhos em vez de chaves
+
+fn looks_like_path(text: &str) -> bool {
+    text.contains(['/', '\\', ':']) || text.starts_with(['$', '.'])
+}
+
+// "${ROOT}/docs/" do autocompletar vira "${ROOT}/docs" (mas "/" continua "/")
+fn trim_separator(path: &str) -> String {
+    match path.trim_end_matches(['/', '\\']) {
+        "" => path.to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+fn common_prefix(items: &[String]) -> String {
+    let mut prefix = items.first().cloned().unwrap_or_default();
+    for item in items.iter().skip(1) {
+        let len = prefix
+            .char_indices()
+            .zip(item.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((i, a), _)| i + a.len_utf8());
+        prefix.truncate(len);
+    }
+    prefix
+}
+
+// Data do set como aparece na lista (e no filtro); sets sem data ficam vazios
+fn format_created(created_at: u64) -> String {
+    match Local.timestamp_opt(created_at as i64, 0).single() {
+        Some(t) if created_at > 0 => t.format("%Y-%m-%d %H:%M").to_string(),
+        _ => String::new(),
+    }
+}
+
+// Cada termo do filtro precisa aparecer no nome, no ID ou na data
+fn set_matches(man: &SetManifest, terms: &[String]) -> bool {
+    let name = man.name.to_lowercase();
+    let id = man.id.to_lowercase();
+    let created = format_created(man.created_at);
+    terms
+        .iter()
+        .all(|t| name.contains(t) || id.contains(t) || created.contains(t))
+}
+
 struct App {
-    config: BlkConfig,
-    app_root: PathBuf,
+    repo: Repository,
 
+    // Só os sets que passam no filtro, na mesma ordem de `repo.sets()`
     items: Vec<String>,
     ids: Vec<String>,
     manifests_cache: HashMap<String, SetManifest>,
+    filter: TextInput,
 
     state: ListState,
 
     is_processing: bool,
     progress: u16,
+    file_progress: Option<FileProgress>,
+    file_started: Instant,
+    op_name: &'static str,
+    op_warnings: Vec<String>,
     status_msg: String,
     
     // Contador para animação do spinner
     spinner_tick: u64,
 
-    input_text: String,
+    input: TextInput,
     input_mode: InputMode,
 
     delete_target_id: String,
@@ -74,15 +238,55 @@ struct App {
     config_temp_exclusions: Vec<String>,
     config_focus: ConfigFocus,
     config_state: ListState,
+    // AddingPath: candidatos do [Tab], aviso atual e o texto já avisado
+    // (um segundo Enter no mesmo texto confirma)
+    completions: Vec<String>,
+    add_message: String,
+    add_confirm: Option<String>,
+    path_probe: Option<ScopeStatus>,
+    path_probe_receiver: Option<mpsc::Receiver<ScopeStatus>>,
+    path_edited_at: Option<Instant>,
 
     tree_scroll: u16,
     pending_save_after_config: bool,
 
+    export_target_id: String,
+    export_flatten: bool,
+
+    import_path: PathBuf,
+    import_scopes: Vec<String>,
+    import_map: HashMap<String, String>,
+
+    remote_push: bool,
+    remote_target_id: String,
+
+    pathmap_keys: Vec<String>,
+    pathmap_state: ListState,
+    pathmap_status: HashMap<String, ScopeStatus>,
+    pathmap_receiver: Option<mpsc::Receiver<ScopeStatus>>,
+    // None = adicionando uma chave nova (Key=Path)
+    pathmap_edit_key: Option<String>,
+    pathmap_remove_msg: String,
+
+    // Navegador de conteúdo: `contents_dir` é "Scope/pasta", "" na raiz
+    contents_id: String,
+    contents_full: bool,
+    contents: Vec<ContentEntry>,
+    contents_summary: String,
+    contents_dir: String,
+    contents_rows: Vec<ContentRow>,
+    contents_state: ListState,
+    contents_receiver: Option<mpsc::Receiver<BlkResult<Vec<ContentEntry>>>>,
+
     diff_summary: DiffSummary,
     active_set_id: Option<String>,
 
-    receiver: Option<mpsc::Receiver<(f32, String)>>,
-    diff_receiver: Option<mpsc::Receiver<DiffSummary>>,
+    receiver: Option<mpsc::Receiver<EngineEvent>>,
+    /// Só existe enquanto roda uma operação que aceita cancelamento
+    cancel: Option<CancelToken>,
+    quit_after_op: bool,
+    diff_receiver: Option<mpsc::Receiver<BlkResult<DiffSummary>>>,
+    announce_check: bool,
     reload_needed: bool,
     init_thread_spawned: bool,
 }
@@ -94,17 +298,21 @@ impl App {
 
         if !blk_path.exists() {
             return App {
-                config: BlkConfig::default(),
-                app_root,
+                repo: Repository::with_config(app_root, BlkConfig::default()),
                 items: vec![],
                 ids: vec![],
                 manifests_cache: HashMap::new(),
+                filter: TextInput::default(),
                 state: ListState::default(),
                 is_processing: true,
                 progress: 0,
+                file_progress: None,
+                file_started: Instant::now(),
+                op_name: "init",
+                op_warnings: vec![],
                 status_msg: "Initializing...".into(),
                 spinner_tick: 0,
-                input_text: String::new(),
+                input: TextInput::default(),
                 input_mode: InputMode::Initializing,
                 delete_target_id: String::new(),
                 delete_warning_msg: String::new(),
@@ -113,12 +321,42 @@ impl App {
                 config_temp_exclusions: vec![],
                 config_focus: ConfigFocus::Scopes,
                 config_state: ListState::default(),
+                completions: vec![],
+                add_message: String::new(),
+                add_confirm: None,
+                path_probe: None,
+                path_probe_receiver: None,
+                path_edited_at: None,
                 tree_scroll: 0,
                 pending_save_after_config: false,
+                export_target_id: String::new(),
+                export_flatten: false,
+                import_path: PathBuf::new(),
+                import_scopes: vec![],
+                import_map: HashMap::new(),
+                remote_push: false,
+                remote_target_id: String::new(),
+                pathmap_keys: vec![],
+                pathmap_state: ListState::default(),
+                pathmap_status: HashMap::new(),
+                pathmap_receiver: None,
+                pathmap_edit_key: None,
+                pathmap_remove_msg: String::new(),
+                contents_id: String::new(),
+                contents_full: false,
+                contents: vec![],
+                contents_summary: String::new(),
+                contents_dir: String::new(),
+                contents_rows: vec![],
+                contents_state: ListState::default(),
+                contents_receiver: None,
                 diff_summary: DiffSummary::default(),
                 active_set_id: None,
                 receiver: None,
+                cancel: None,
+                quit_after_op: false,
                 diff_receiver: None,
+                announce_check: false,
                 reload_needed: false,
                 init_thread_spawned: false,
             };
@@ -128,58 +366,48 @@ impl App {
     }
 
     fn load_initial_state(app_root: PathBuf) -> Self {
-        let config_path = app_root.join(".blk").join("config.json");
-        let config: BlkConfig = if config_path.exists() {
-            let txt = fs::read_to_string(config_path).unwrap_or_else(|_| "{}".into());
-            serde_json::from_str(&txt).unwrap_or_default()
-        } else {
-            BlkConfig::default()
+        // Config ilegível: nada de seguir com a padrão (e sobrescrever a
+        // original no próximo save); fica na tela de preparo com o erro
+        let (repo, failed) = match Repository::open(&app_root) {
+            Ok(repo) => (repo, None),
+            Err(e) => (
+                Repository::with_config(app_root, BlkConfig::default()),
+                Some(format!("❌ {e} | [Q] Quit")),
+            ),
+        };
+        let status_msg = match (&failed, repo.unreadable_sets()) {
+            (Some(msg), _) => msg.clone(),
+            (None, []) => match repo.upgrade_note() {
+                Some(note) => note.to_string(),
+                None => "Ready. [F5] Check modifications.".to_string(),
+            },
+            (None, [first, ..]) => format!(
+                "⚠ {} unreadable set(s), first: {first}",
+                repo.unreadable_sets().len()
+            ),
         };
 
-        let sets_root = app_root.join(".blk").join("sets");
         let mut cache = HashMap::new();
-        let mut list: Vec<SetManifest> = Vec::new();
-
-        if let Ok(entries) = fs::read_dir(sets_root) {
-            for entry in entries.flatten() {
-                if entry.path().extension().map_or(false, |e| e == "json") {
-                    if let Ok(txt) = fs::read_to_string(entry.path()) {
-                        if let Ok(man) = serde_json::from_str::<SetManifest>(&txt) {
-                            cache.insert(man.id.clone(), man.clone());
-                            list.push(man);
-                        }
-                    }
-                }
-            }
-        }
-
-        list.sort_by(|a, b| b.created_at.cmp(&a.created_at));
-
-        let mut items = Vec::new();
-        let mut ids = Vec::new();
-
-        for m in list {
-            items.push(m.name);
-            ids.push(m.id);
-        }
-
-        if items.is_empty() {
-            items.push("BLK Zero Set".into());
-            ids.push("".into());
+        for m in repo.sets() {
+            cache.insert(m.id.clone(), m.clone());
         }
 
         let mut app = App {
-            config,
-            app_root,
-            items,
-            ids,
+            repo,
+            items: vec![],
+            ids: vec![],
             manifests_cache: cache,
+            filter: TextInput::default(),
             state: ListState::default(),
             is_processing: false,
             progress: 0,
-            status_msg: "Ready. [F5] Check modifications.".into(),
+            file_progress: None,
+            file_started: Instant::now(),
+            op_name: "",
+            op_warnings: vec![],
+            status_msg,
             spinner_tick: 0,
-            input_text: String::new(),
+            input: TextInput::default(),
             input_mode: InputMode::Normal,
             delete_target_id: String::new(),
             delete_warning_msg: String::new(),
@@ -188,44 +416,137 @@ impl App {
             config_temp_exclusions: vec![],
             config_focus: ConfigFocus::Scopes,
             config_state: ListState::default(),
+            completions: vec![],
+            add_message: String::new(),
+            add_confirm: None,
+            path_probe: None,
+            path_probe_receiver: None,
+            path_edited_at: None,
             tree_scroll: 0,
             pending_save_after_config: false,
+            export_target_id: String::new(),
+            export_flatten: false,
+            import_path: PathBuf::new(),
+            import_scopes: vec![],
+            import_map: HashMap::new(),
+            remote_push: false,
+            remote_target_id: String::new(),
+            pathmap_keys: vec![],
+            pathmap_state: ListState::default(),
+            pathmap_status: HashMap::new(),
+            pathmap_receiver: None,
+            pathmap_edit_key: None,
+            pathmap_remove_msg: String::new(),
+            contents_id: String::new(),
+            contents_full: false,
+            contents: vec![],
+            contents_summary: String::new(),
+            contents_dir: String::new(),
+            contents_rows: vec![],
+            contents_state: ListState::default(),
+            contents_receiver: None,
             diff_summary: DiffSummary::default(),
             active_set_id: None,
             receiver: None,
+            cancel: None,
+            quit_after_op: false,
             diff_receiver: None,
+            announce_check: false,
             reload_needed: false,
             init_thread_spawned: true,
         };
 
-        app.state.select(Some(0));
+        app.apply_filter();
+        if failed.is_some() {
+            app.input_mode = InputMode::Initializing;
+        }
         app
     }
 
     fn refresh_list(&mut self) {
-        let new = App::load_initial_state(self.app_root.clone());
-        self.items = new.items;
-        self.ids = new.ids;
+        let new = App::load_initial_state(self.repo.root().to_path_buf());
+        if let InputMode::Initializing = new.input_mode {
+            *self = new;
+            return;
+        }
         self.manifests_cache = new.manifests_cache;
-        self.config = new.config;
-        self.state.select(Some(0));
-        self.tree_scroll = 0;
+        self.repo = new.repo;
+        // O filtro continua; a seleção volta para o set mais novo
+        self.state.select(None);
+        self.apply_filter();
         self.reload_needed = false;
         self.input_mode = InputMode::Normal;
     }
 
     fn trigger_auto_init(&mut self) {
         self.init_thread_spawned = true;
-        let root = self.app_root.clone();
+        let root = self.repo.root().to_path_buf();
         let (tx, rx) = mpsc::channel();
         self.receiver = Some(rx);
 
         thread::spawn(move || {
-            engine_auto_init(&root, tx);
+            let outcome = Repository::init(&root, tx.clone());
+            send_outcome(&root, &tx, outcome);
         });
     }
 
+    /// Refaz `items`/`ids` com os sets que passam no filtro. O set
+    /// selecionado continua selecionado se ainda aparece; senão, o primeiro.
+    fn apply_filter(&mut self) {
+        let selected = self
+            .state
+            .selected()
+            .and_then(|i| self.ids.get(i))
+            .filter(|id| !id.is_empty())
+            .cloned();
+        let terms: Vec<String> = self
+            .filter
+            .text()
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect();
+
+        self.items.clear();
+        self.ids.clear();
+        for m in self.repo.sets() {
+            if set_matches(m, &terms) {
+                self.items.push(m.name.clone());
+                self.ids.push(m.id.clone());
+            }
+        }
+        // Sem sets de verdade (não só escondidos pelo filtro)
+        if self.items.is_empty() && terms.is_empty() {
+            self.items.push("BLK Zero Set".into());
+            self.ids.push("".into());
+        }
+
+        let pos = selected
+            .as_ref()
+            .and_then(|id| self.ids.iter().position(|i| i == id));
+        if self.ids.is_empty() {
+            self.state.select(None);
+        } else {
+            self.state.select(Some(pos.unwrap_or(0)));
+        }
+        if pos.is_none() {
+            self.tree_scroll = 0;
+        }
+    }
+
+    fn start_search(&mut self) {
+        self.input_mode = InputMode::Searching;
+    }
+
+    fn clear_filter(&mut self) {
+        self.filter.clear();
+        self.apply_filter();
+        self.input_mode = InputMode::Normal;
+    }
+
     fn next(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
         let i = match self.state.selected() {
             Some(i) => {
                 if i >= self.items.len() - 1 {
@@ -241,6 +562,9 @@ impl App {
     }
 
     fn previous(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
         let i = match self.state.selected() {
             Some(i) => {
                 if i == 0 {
@@ -349,56 +673,202 @@ impl App {
     }
 
     fn config_start_add(&mut self) {
-        self.input_text.clear();
+        self.input.clear();
+        self.completions.clear();
+        self.add_message.clear();
+        self.add_confirm = None;
+        self.path_probe = None;
+        self.path_edited_at = None;
         self.input_mode = InputMode::AddingPath;
     }
 
+    fn adding_path_edited(&mut self) {
+        self.completions.clear();
+        self.add_message.clear();
+        self.path_edited_at = Some(Instant::now());
+    }
+
+    fn parse_scope_entry(&self, entry: &str) -> Result<ScopeEntry, String> {
+        if let Some((key, path)) = entry.split_once('=') {
+            let key = key.trim().to_string();
+            let path = trim_separator(path.trim().trim_matches('"'));
+            if key.is_empty() {
+                return Err("Missing scope name before '='".into());
+            }
+            if path.is_empty() {
+                return Err(format!("Missing path after '{key}='"));
+            }
+            return Ok(ScopeEntry::Path { key, path });
+        }
+
+        let raw = entry.trim_matches('"');
+        if self.repo.config().path_map.contains_key(raw) {
+            return Ok(ScopeEntry::Key(raw.to_string()));
+        }
+        if !looks_like_path(raw) {
+            return Err(format!(
+                "Unknown scope '{raw}'. Use Key=Path, a folder path, or [Tab] to complete"
+            ));
+        }
+        // Caminho puro: a chave vem do nome da pasta
+        let path = trim_separator(raw);
+        let key = Path::new(&path)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .map(|n| n.replace(' ', "_"))
+            .unwrap_or_else(|| "Extra".to_string());
+        Ok(ScopeEntry::Path { key, path })
+    }
+
+    // Texto do caminho sendo digitado, se a entrada é um caminho
+    fn adding_path_target(&self) -> Option<String> {
+        match self.config_focus {
+            ConfigFocus::Scopes => match self.parse_scope_entry(self.input.text().trim()) {
+                Ok(ScopeEntry::Path { path, .. }) => Some(path),
+                _ => None,
+            },
+            ConfigFocus::Exclusions => None,
+        }
+    }
+
+    /// Indicador ao vivo abaixo do campo: existe / é pasta / tamanho.
+    fn adding_path_hint(&self) -> Option<(String, Color)> {
+        let entry = self.input.text().trim();
+        if entry.is_empty() {
+            return None;
+        }
+        if let ConfigFocus::Exclusions = self.config_focus {
+            return None;
+        }
+        let (key, path) = match self.parse_scope_entry(entry) {
+            Ok(ScopeEntry::Key(key)) => {
+                let raw = self.repo.config().path_map[&key].to_string_lossy().to_string();
+                return Some((format!("✔ Existing scope '{key}' → {raw}"), Color::Green));
+            }
+            Ok(ScopeEntry::Path { key, path }) => (key, path),
+            Err(e) => return Some((e, Color::DarkGray)),
+        };
+
+        let resolved = match expand_path(Path::new(&path), self.repo.root()) {
+            Ok(resolved) => resolved,
+            Err(e) => return Some((format!("⚠ {e}"), Color::Red)),
+        };
+        let meta = match std::fs::metadata(&resolved) {
+            Ok(meta) => meta,
+            Err(_) => {
+                return Some((format!("✖ {} doesn't exist", resolved.to_string_lossy()), Color::Red))
+            }
+        };
+        if !meta.is_dir() {
+            return Some((format!("✖ Not a folder ({})", format_bytes(meta.len())), Color::Red));
+        }
+
+        let size = match &self.path_probe {
+            Some(probe) if probe.raw == Path::new(&path) => {
+                format!("{} files, {}", probe.files, format_bytes(probe.bytes))
+            }
+            _ => "measuring…".to_string(),
+        };
+        match self.repo.config().path_map.get(&key) {
+            Some(current) if current != Path::new(&path) => Some((
+                format!("⚠ Folder, {size} | '{key}' already maps to {}", current.to_string_lossy()),
+                Color::Yellow,
+            )),
+            _ => Some((format!("✔ Folder '{key}', {size}"), Color::Green)),
+        }
+    }
+
+    // Mede o caminho só quando a digitação para: cada medida percorre a pasta inteira
+    fn probe_adding_path(&mut self) {
+        let settled = self
+            .path_edited_at
+            .is_some_and(|t| t.elapsed() >= Duration::from_millis(400));
+        if !settled {
+            return;
+        }
+        self.path_edited_at = None;
+        let path = match self.adding_path_target() {
+            Some(path) => path,
+            None => return,
+        };
+
+        let (tx, rx) = mpsc::channel();
+        self.path_probe_receiver = Some(rx);
+        let repo = self.repo.clone();
+        thread::spawn(move || {
+            tx.send(repo.probe_path(&path)).ok();
+        });
+    }
+
+    fn complete_adding_path(&mut self) {
+        if let ConfigFocus::Exclusions = self.config_focus {
+            return;
+        }
+        let (head, tail) = match self.input.text().split_once('=') {
+            Some((key, path)) => (format!("{key}="), path.to_string()),
+            None => (String::new(), self.input.text().to_string()),
+        };
+
+        let candidates: Vec<String> = if head.is_empty() && !looks_like_path(&tail) {
+            let lower = tail.to_lowercase();
+            self.repo
+                .scope_keys()
+                .into_iter()
+                .filter(|k| k.to_lowercase().starts_with(&lower))
+                .collect()
+        } else {
+            complete_path(&tail, self.repo.root())
+        };
+
+        match candidates.as_slice() {
+            [] => self.add_message = "No matches".into(),
+            [only] => {
+                self.input.set(format!("{head}{only}"));
+                self.adding_path_edited();
+            }
+            many => {
+                let prefix = common_prefix(many);
+                if prefix.chars().count() > tail.chars().count() {
+                    self.input.set(format!("{head}{prefix}"));
+                }
+                self.adding_path_edited();
+                self.completions = candidates;
+            }
+        }
+    }
+
     fn config_confirm_add(&mut self) {
-        let entry = self.input_text.trim().to_string();
-        if !entry.is_empty() {
-            match self.config_focus {
-                ConfigFocus::Scopes => {
-                    // CORREÇÃO: Lógica para aceitar "Key=Path" OU apenas "Path"
-                    let (key_clean, path_clean) = if let Some((key, path)) = entry.split_once('=') {
-                        (key.trim().to_string(), path.trim().to_string())
-                    } else {
-                        // Trata caminho puro
-                        let raw = entry.trim_matches('"').to_string();
-                        if raw.contains(':') || raw.contains('\\') || raw.contains('/') {
-                            // É um caminho. Extrair nome da pasta para ser a chave.
-                            let pb = PathBuf::from(&raw);
-                            let folder_name = pb.file_name()
-                                .and_then(|n| n.to_str())
-                                .map(|s| s.replace(" ", "_")) // Sanitiza espaços
-                                .unwrap_or_else(|| "Extra".to_string());
-                            
-                            (folder_name, raw)
-                        } else {
-                            // É apenas uma chave existente ou nome lógico
-                            (raw.clone(), String::new()) 
-                        }
-                    };
+        let entry = self.input.text().trim().to_string();
+        if entry.is_empty() {
+            return;
+        }
 
-                    // Se temos um caminho, atualiza o mapa global
-                    if !path_clean.is_empty() {
-                         engine_update_global_path(
-                            &self.app_root,
-                            key_clean.clone(),
-                            path_clean.clone(),
-                        );
-                        self.config
-                            .path_map
-                            .insert(key_clean.clone(), PathBuf::from(path_clean));
+        match self.config_focus {
+            ConfigFocus::Scopes => {
+                let key = match self.parse_scope_entry(&entry) {
+                    Ok(ScopeEntry::Key(key)) => key,
+                    Ok(ScopeEntry::Path { key, path }) => match self.accept_scope_path(&entry, &key, &path) {
+                        Some(()) => key,
+                        None => return,
+                    },
+                    Err(e) => {
+                        self.add_message = format!("❌ {e}");
+                        return;
                     }
-                    
-                    // IMPORTANTE: Adiciona apenas ao escopo local do manifesto sendo editado
-                    self.config_temp_scopes.push(key_clean);
+                };
+
+                // Só entra no escopo local do manifesto sendo editado
+                if !self.config_temp_scopes.contains(&key) {
+                    self.config_temp_scopes.push(key);
                 }
-                ConfigFocus::Exclusions => self.config_temp_exclusions.push(entry),
             }
+            ConfigFocus::Exclusions => self.config_temp_exclusions.push(entry),
         }
 
-        self.input_text.clear();
+        self.input.clear();
+        self.completions.clear();
+        self.add_message.clear();
+        self.add_confirm = None;
         self.input_mode = InputMode::Configuring;
         let new_len = match self.config_focus {
             ConfigFocus::Scopes => self.config_temp_scopes.len(),
@@ -408,6 +878,55 @@ impl App {
             .select(Some(if new_len > 0 { new_len - 1 } else { 0 }));
     }
 
+    /// Valida e grava `key` → `path` no mapa global; `None` = não aceito.
+    fn accept_scope_path(&mut self, entry: &str, key: &str, path: &str) -> Option<()> {
+        let saved = self
+            .vet_scope_path(entry, key, path)
+            .and_then(|_| self.repo.set_path(key, path).map_err(|e| format!("❌ {e}")));
+        match saved {
+            Ok(()) => Some(()),
+            Err(msg) => {
+                self.add_message = msg;
+                None
+            }
+        }
+    }
+
+    /// Confere `key` → `path` antes de gravar (adição na config do set e no
+    /// editor do path map). Pasta inexistente ou chave já apontando para outro
+    /// lugar pedem um segundo Enter com a mesma `entry`; `Err` traz a mensagem.
+    fn vet_scope_path(&mut self, entry: &str, key: &str, path: &str) -> Result<(), String> {
+        let resolved = expand_path(Path::new(path), self.repo.root()).map_err(|e| format!("❌ {e}"))?;
+        let current = self.repo.config().path_map.get(key);
+        if current.is_some_and(|c| c == Path::new(path)) {
+            return Ok(());
+        }
+
+        let warning = match std::fs::metadata(&resolved) {
+            Ok(meta) if !meta.is_dir() => {
+                return Err(format!("❌ {} is a file; scopes must be folders", resolved.to_string_lossy()));
+            }
+            Ok(_) => None,
+            Err(_) => Some(format!("⚠ {} doesn't exist.", resolved.to_string_lossy())),
+        };
+        let warning = match (warning, current) {
+            (w, Some(c)) => Some(format!(
+                "{}⚠ '{key}' already maps to {} (used by {} set(s)).",
+                w.map(|w| format!("{w} ")).unwrap_or_default(),
+                c.to_string_lossy(),
+                self.repo.sets_using_scope(key).len()
+            )),
+            (w, None) => w,
+        };
+        if let Some(warning) = warning {
+            if self.add_confirm.as_deref() != Some(entry) {
+                self.add_confirm = Some(entry.to_string());
+                return Err(format!("{warning} [Enter] again to confirm"));
+            }
+        }
+        Ok(())
+    }
+
     fn config_delete_selected(&mut self) {
         match self.config_focus {
             ConfigFocus::Scopes => {
@@ -463,38 +982,318 @@ impl App {
 
         let (tx, rx) = mpsc::channel();
         self.receiver = Some(rx);
-        let root = self.app_root.clone();
+        let mut repo = self.repo.clone();
 
         thread::spawn(move || {
-            engine_update_manifest(&root, id, scopes, exc, tx);
+            let outcome = repo.update_set(&id, scopes, exc, tx.clone());
+            send_outcome(repo.root(), &tx, outcome);
         });
     }
 
-    // ------------------------ diff checking --------------------------------
+    fn cycle_compression_profile(&mut self) {
+        let profile = self.repo.config().compression.profile.next();
+        self.status_msg = match self.repo.set_compression(profile) {
+            Ok(()) => format!("Compression profile: {}", profile.label()),
+            Err(e) => format!("❌ {e}"),
+        };
+    }
 
-    fn check_dir_status(&mut self) {
-        let (scopes, exclusions) = match &self.active_set_id {
-            Some(id) => {
-                if let Some(m) = self.manifests_cache.get(id) {
-                    (m.scopes.clone(), m.exclusions.clone())
-                } else {
-                    // Fallback apenas se não achar o manifesto
-                    (self.config.path_map.keys().cloned().collect(), vec![])
+    // --------------------------- path map ----------------------------------
+
+    fn open_path_map(&mut self) {
+        self.pathmap_keys = self.repo.scope_keys();
+        self.pathmap_state.select(if self.pathmap_keys.is_empty() {
+            None
+        } else {
+            Some(0)
+        });
+        self.pathmap_status.clear();
+        self.scan_missing_scopes();
+        self.input_mode = InputMode::PathMap;
+    }
+
+    fn close_path_map(&mut self) {
+        self.pathmap_receiver = None;
+        self.input_mode = InputMode::Normal;
+        // Um caminho trocado muda o que o diff enxerga
+        self.check_dir_status(false);
+    }
+
+    // Medir um scope grande demora: roda em background, um scope por vez
+    fn scan_missing_scopes(&mut self) {
+        let keys: Vec<String> = self
+            .pathmap_keys
+            .iter()
+            .filter(|k| !self.pathmap_status.contains_key(*k))
+            .cloned()
+            .collect();
+        let (tx, rx) = mpsc::channel();
+        self.pathmap_receiver = Some(rx);
+        let repo = self.repo.clone();
+
+        thread::spawn(move || {
+            for key in keys {
+                if let Some(status) = repo.scope_status(&key) {
+                    if tx.send(status).is_err() {
+                        break;
+                    }
                 }
             }
-            // Se nenhum set está ativo (inicialização), pega tudo.
-            None => (self.config.path_map.keys().cloned().collect(), vec![]),
+        });
+    }
+
+    fn pathmap_selected(&self) -> Option<String> {
+        self.pathmap_state
+            .selected()
+            .and_then(|i| self.pathmap_keys.get(i))
+            .cloned()
+    }
+
+    fn pathmap_move(&mut self, down: bool) {
+        let len = self.pathmap_keys.len();
+        if len == 0 {
+            return;
+        }
+        let i = self.pathmap_state.selected().unwrap_or(0);
+        let next = if down { (i + 1) % len } else { (i + len - 1) % len };
+        self.pathmap_state.select(Some(next));
+    }
+
+    fn pathmap_start_edit(&mut self) {
+        if let Some(key) = self.pathmap_selected() {
+            self.input.set(self.repo.config().path_map[&key].to_string_lossy());
+            self.pathmap_edit_key = Some(key);
+            self.add_confirm = None;
+            self.status_msg.clear();
+            self.input_mode = InputMode::EditingScopePath;
+        }
+    }
+
+    fn pathmap_start_add(&mut self) {
+        self.input.clear();
+        self.pathmap_edit_key = None;
+        self.add_confirm = None;
+        self.status_msg.clear();
+        self.input_mode = InputMode::EditingScopePath;
+    }
+
+    fn pathmap_confirm_edit(&mut self) {
+        let entry = self.input.text().trim().to_string();
+        let (key, path) = match &self.pathmap_edit_key {
+            Some(key) => (key.clone(), entry.trim_matches('"').to_string()),
+            None => match entry.split_once('=') {
+                Some((key, path)) => (key.trim().to_string(), path.trim().trim_matches('"').to_string()),
+                None => {
+                    self.status_msg = "Use Key=Path to add a scope".into();
+                    return;
+                }
+            },
+        };
+        if key.is_empty() || path.is_empty() {
+            return;
+        }
+
+        // Mesma checagem da adição pela config do set
+        if let Err(msg) = self.vet_scope_path(&entry, &key, &path) {
+            self.status_msg = msg;
+            return;
+        }
+        if let Err(e) = self.repo.set_path(&key, &path) {
+            self.status_msg = format!("❌ {e}");
+            return;
+        }
+        self.add_confirm = None;
+        self.status_msg = format!("Scope '{key}' → {path}");
+        self.pathmap_keys = self.repo.scope_keys();
+        self.pathmap_state
+            .select(self.pathmap_keys.iter().position(|k| *k == key));
+        self.pathmap_status.remove(&key);
+        self.scan_missing_scopes();
+
+        self.input.clear();
+        self.input_mode = InputMode::PathMap;
+    }
+
+    fn pathmap_start_remove(&mut self) {
+        let key = match self.pathmap_selected() {
+            Some(key) => key,
+            None => return,
+        };
+        // Sem o mapeamento o restore desses sets cairia dentro do Root
+        let users = self.repo.sets_using_scope(&key);
+        if !users.is_empty() {
+            let names: Vec<&str> = users.iter().map(|m| m.name.as_str()).collect();
+            self.status_msg = format!(
+                "❌ '{key}' is used by {} set(s): {}. Take it out of their config first",
+                users.len(),
+                names.join(", ")
+            );
+            return;
+        }
+        self.pathmap_remove_msg = format!("Remove scope '{key}' from the path map? (type 'y')");
+        self.input.clear();
+        self.input_mode = InputMode::ConfirmRemoveScope;
+    }
+
+    fn pathmap_confirm_remove(&mut self) {
+        if !self.input.text().trim().eq_ignore_ascii_case("y") {
+            return;
+        }
+        let key = match self.pathmap_selected() {
+            Some(key) => key,
+            None => return,
+        };
+
+        self.status_msg = match self.repo.remove_path(&key) {
+            Ok(()) => format!("Scope '{key}' removed"),
+            Err(e) => format!("❌ {e}"),
+        };
+        self.pathmap_keys = self.repo.scope_keys();
+        self.pathmap_status.remove(&key);
+        let last = self.pathmap_keys.len().checked_sub(1);
+        self.pathmap_state
+            .select(self.pathmap_state.selected().and_then(|i| last.map(|l| i.min(l))));
+
+        self.input.clear();
+        self.input_mode = InputMode::PathMap;
+    }
+
+    // ------------------------ contents browser -----------------------------
+
+    fn open_contents(&mut self) {
+        let idx = self.state.selected().unwrap_or(0);
+        let id = match self.ids.get(idx) {
+            Some(id) if !id.is_empty() => id.clone(),
+            _ => return,
+        };
+        self.contents_id = id;
+        self.contents_full = false;
+        self.contents_dir.clear();
+        self.load_contents();
+        self.input_mode = InputMode::Contents;
+    }
+
+    fn close_contents(&mut self) {
+        self.contents_receiver = None;
+        self.contents.clear();
+        self.contents_rows.clear();
+        self.input_mode = InputMode::Normal;
+    }
+
+    // Ler o índice de uma cadeia grande demora um pouco: vai para background
+    fn load_contents(&mut self) {
+        self.contents.clear();
+        self.contents_rows.clear();
+        self.contents_state.select(None);
+        self.contents_summary = "Reading snapshot index...".into();
+
+        let (tx, rx) = mpsc::channel();
+        self.contents_receiver = Some(rx);
+        let repo = self.repo.clone();
+        let id = self.contents_id.clone();
+        let full = self.contents_full;
+
+        thread::spawn(move || {
+            tx.send(repo.contents(&id, full)).ok();
+        });
+    }
+
+    fn contents_loaded(&mut self, result: BlkResult<Vec<ContentEntry>>) {
+        self.contents = match result {
+            Ok(contents) => contents,
+            Err(e) => {
+                self.contents_summary = format!("❌ {e}");
+                return;
+            }
+        };
+
+        let live = self.contents.iter().filter(|e| !e.deleted && e.kind != EntryKind::Dir);
+        let (files, bytes) = live.fold((0, 0), |(n, b), e| (n + 1, b + e.size));
+        let deleted = self.contents.iter().filter(|e| e.deleted).count();
+        self.contents_summary = if self.contents.is_empty() && self.contents_full {
+            "Empty: the chain restores no files.".to_string()
+        } else if self.contents.is_empty() {
+            "Empty: nothing changed when this set was saved.".to_string()
+        } else if self.contents_full {
+            format!("{files} files, {}", format_bytes(bytes))
+        } else {
+            format!("{files} files, {} | {deleted} deleted", format_bytes(bytes))
+        };
+
+        // A pasta aberta pode não existir na outra visão
+        self.contents_show_dir(None);
+        if self.contents_rows.is_empty() && !self.contents_dir.is_empty() {
+            self.contents_dir.clear();
+            self.contents_show_dir(None);
+        }
+    }
+
+    fn contents_show_dir(&mut self, select: Option<&str>) {
+        self.contents_rows = content_rows(&self.contents, &self.contents_dir, self.contents_full);
+        let pos = select.and_then(|name| self.contents_rows.iter().position(|r| r.name == name));
+        self.contents_state.select(if self.contents_rows.is_empty() {
+            None
+        } else {
+            Some(pos.unwrap_or(0))
+        });
+    }
+
+    fn contents_toggle_full(&mut self) {
+        self.contents_full = !self.contents_full;
+        self.load_contents();
+    }
+
+    fn contents_move(&mut self, down: bool) {
+        let len = self.contents_rows.len();
+        if len == 0 {
+            return;
+        }
+        let i = self.contents_state.selected().unwrap_or(0);
+        let next = if down { (i + 1) % len } else { (i + len - 1) % len };
+        self.contents_state.select(Some(next));
+    }
+
+    fn contents_open(&mut self) {
+        let row = match self.contents_state.selected().and_then(|i| self.contents_rows.get(i)) {
+            Some(row) if row.kind == EntryKind::Dir => row,
+            _ => return,
+        };
+        if !self.contents_dir.is_empty() {
+            self.contents_dir.push('/');
+        }
+        self.contents_dir.push_str(&row.name);
+        self.contents_show_dir(None);
+    }
+
+    fn contents_up(&mut self) {
+        if self.contents_dir.is_empty() {
+            return;
+        }
+        let (parent, name) = match self.contents_dir.rsplit_once('/') {
+            Some((parent, name)) => (parent.to_string(), name.to_string()),
+            None => (String::new(), self.contents_dir.clone()),
         };
+        self.contents_dir = parent;
+        self.contents_show_dir(Some(&name));
+    }
+
+    // ------------------------ diff checking --------------------------------
 
+    fn check_dir_status(&mut self, announce: bool) {
+        // Sem set ativo (inicialização) o repositório compara todo o path_map
+        let active = self.active_set_id.clone();
         let (tx, rx) = mpsc::channel();
         self.diff_receiver = Some(rx);
 
-        let cfg = self.config.clone();
-        let root = self.app_root.clone();
-        self.status_msg = "Checking modifications...".into();
+        let repo = self.repo.clone();
+        // Depois de uma operação o resultado dela continua na barra de status
+        self.announce_check = announce;
+        if announce {
+            self.status_msg = "Checking modifications...".into();
+        }
 
         thread::spawn(move || {
-            engine_check_changes(&root, cfg, scopes, exclusions, tx);
+            tx.send(repo.diff(active.as_deref())).ok();
         });
     }
 
@@ -511,12 +1310,7 @@ impl App {
         }
 
         self.active_set_id = Some(id.clone());
-        let man = self.manifests_cache.get(&id).unwrap();
-        let scopes = man.scopes.clone();
-        let exclusions = man.exclusions.clone();
-
         let chain = self.resolve_dependencies(&id);
-        let ids_clone = chain.clone();
 
         self.is_processing = true;
         self.progress = 0;
@@ -524,16 +1318,17 @@ impl App {
 
         let (tx, rx) = mpsc::channel();
         self.receiver = Some(rx);
-        let cfg = self.config.clone();
-        let root = self.app_root.clone();
+        let cancel = self.new_cancel_token();
+        let repo = self.repo.clone();
 
         thread::spawn(move || {
-            engine_restore_chain(&root, cfg, ids_clone, scopes, exclusions, tx);
+            let outcome = repo.restore(&id, cancel, tx.clone());
+            send_outcome(repo.root(), &tx, outcome);
         });
     }
 
     fn action_save(&mut self) {
-        let name = self.input_text.clone();
+        let name = self.input.text().to_string();
         if name.trim().is_empty() {
             return;
         }
@@ -547,28 +1342,23 @@ impl App {
         let parent_id = if current_id.is_empty() {
             None
         } else {
-            Some(current_id.clone())
-        };
-
-        let (scopes, exclusions) = if let Some(man) = self.manifests_cache.get(&current_id) {
-            (man.scopes.clone(), man.exclusions.clone())
-        } else {
-            (self.config.path_map.keys().cloned().collect(), vec![])
+            Some(current_id)
         };
 
         self.input_mode = InputMode::Normal;
-        self.input_text.clear();
+        self.input.clear();
         self.is_processing = true;
         self.status_msg = format!("Saving '{name}'...");
         self.reload_needed = true;
 
         let (tx, rx) = mpsc::channel();
         self.receiver = Some(rx);
-        let cfg = self.config.clone();
-        let root = self.app_root.clone();
+        let cancel = self.new_cancel_token();
+        let mut repo = self.repo.clone();
 
         thread::spawn(move || {
-            engine_save_new_delta(&root, cfg, name, parent_id, scopes, exclusions, tx);
+            let outcome = repo.save(&name, parent_id.as_deref(), cancel, tx.clone());
+            send_outcome(repo.root(), &tx, outcome);
         });
     }
 
@@ -585,7 +1375,7 @@ impl App {
         self.delete_target_id = id.clone();
         let children = self.get_children(&id);
         self.input_mode = InputMode::ConfirmDelete;
-        self.input_text.clear();
+        self.input.clear();
 
         if children.is_empty() {
             self.delete_warning_msg = format!("Delete '{}'? (type 'y')", self.items[idx]);
@@ -598,73 +1388,445 @@ impl App {
         }
     }
 
-    fn action_delete_confirm(&mut self) {
-        let children = self.get_children(&self.delete_target_id);
-        let input_clean = self.input_text.trim();
-        
-        // CORREÇÃO: Lógica de delete flexível
-        let confirmed = if children.is_empty() {
-            // Se for folha, aceita y ou Y
-            input_clean.eq_ignore_ascii_case("y")
-        } else {
-            // Se tiver filhos, exige DELETE exato
-            input_clean == "DELETE"
-        };
-
-        if confirmed {
-            self.input_mode = InputMode::Normal;
-            self.input_text.clear();
-            self.is_processing = true;
-            self.status_msg = "Deleting...".into();
-            self.reload_needed = true;
+    fn action_delete_confirm(&mut self) {
+        let children = self.get_children(&self.delete_target_id);
+        let input_clean = self.input.text().trim();
+        
+        // CORREÇÃO: Lógica de delete flexível
+        let confirmed = if children.is_empty() {
+            // Se for folha, aceita y ou Y
+            input_clean.eq_ignore_ascii_case("y")
+        } else {
+            // Se tiver filhos, exige DELETE exato
+            input_clean == "DELETE"
+        };
+
+        if confirmed {
+            self.input_mode = InputMode::Normal;
+            self.input.clear();
+            self.is_processing = true;
+            self.status_msg = "Deleting...".into();
+            self.reload_needed = true;
+
+            let target = self.delete_target_id.clone();
+            let (tx, rx) = mpsc::channel();
+            self.receiver = Some(rx);
+            let mut repo = self.repo.clone();
+
+            thread::spawn(move || {
+                let outcome = repo.delete(&target, tx.clone());
+                send_outcome(repo.root(), &tx, outcome);
+            });
+        }
+    }
+
+    fn start_export(&mut self) {
+        let idx = self.state.selected().unwrap_or(0);
+        if idx >= self.ids.len() {
+            return;
+        }
+        let id = self.ids[idx].clone();
+        if id.is_empty() {
+            return;
+        }
+
+        self.export_target_id = id.clone();
+        self.export_flatten = false;
+        self.input.set(self.repo.export_path(&id, false).to_string_lossy());
+        self.input_mode = InputMode::ExportPath;
+    }
+
+    fn export_toggle_flatten(&mut self) {
+        // Só troca o caminho se o usuário ainda não mexeu no sugerido
+        let old_default = self.repo.export_path(&self.export_target_id, self.export_flatten);
+        self.export_flatten = !self.export_flatten;
+        if self.input.text() == old_default.to_string_lossy() {
+            let path = self.repo.export_path(&self.export_target_id, self.export_flatten);
+            self.input.set(path.to_string_lossy());
+        }
+    }
+
+    fn action_export(&mut self) {
+        let dest = self.input.text().trim().trim_matches('"').to_string();
+        if dest.is_empty() {
+            return;
+        }
+
+        self.input_mode = InputMode::Normal;
+        self.input.clear();
+        self.is_processing = true;
+        self.status_msg = format!("Exporting '{}'...", self.export_target_id);
+
+        let (tx, rx) = mpsc::channel();
+        self.receiver = Some(rx);
+        let cancel = self.new_cancel_token();
+        let repo = self.repo.clone();
+        let id = self.export_target_id.clone();
+        let flatten = self.export_flatten;
+
+        thread::spawn(move || {
+            let outcome = repo.export(&id, PathBuf::from(dest), flatten, cancel, tx.clone());
+            send_outcome(repo.root(), &tx, outcome);
+        });
+    }
+
+    fn start_import(&mut self) {
+        self.input.clear();
+        self.input_mode = InputMode::ImportPath;
+    }
+
+    fn import_confirm_path(&mut self) {
+        let raw = self.input.text().trim().trim_matches('"').to_string();
+        if raw.is_empty() {
+            return;
+        }
+
+        match read_bundle_manifest(&PathBuf::from(&raw)) {
+            Ok(manifest) => {
+                self.status_msg = format!(
+                    "Bundle '{}': {} set(s)",
+                    manifest.target_id,
+                    manifest.sets.len()
+                );
+                self.import_path = PathBuf::from(raw);
+                self.import_scopes = bundle_scopes(&manifest);
+                self.import_map.clear();
+                self.import_prompt_next_scope();
+            }
+            Err(e) => {
+                self.status_msg = format!("❌ {e}");
+                self.input_mode = InputMode::Normal;
+                self.input.clear();
+            }
+        }
+    }
+
+    // Sugere o mesmo nome quando o scope já existe no path_map local
+    fn import_prompt_next_scope(&mut self) {
+        let scope = &self.import_scopes[self.import_map.len()];
+        let suggested = if self.repo.config().path_map.contains_key(scope) {
+            scope.clone()
+        } else {
+            String::new()
+        };
+        self.input.set(suggested);
+        self.add_confirm = None;
+        self.input_mode = InputMode::ImportScope;
+    }
+
+    fn import_confirm_scope(&mut self) {
+        let entry = self.input.text().trim().to_string();
+        if entry.is_empty() {
+            return;
+        }
+
+        let key = if let Some((key, path)) = entry.split_once('=') {
+            let key = key.trim().to_string();
+            let path = path.trim().trim_matches('"').to_string();
+            if key.is_empty() || path.is_empty() {
+                self.status_msg = "Use Key=Path to add a scope".into();
+                return;
+            }
+            // Pasta que não existe ou chave já mapeada pedem o mesmo [Enter] extra
+            // da tela de paths
+            if let Err(msg) = self.vet_scope_path(&entry, &key, &path) {
+                self.status_msg = msg;
+                return;
+            }
+            if let Err(e) = self.repo.set_path(&key, &path) {
+                self.status_msg = format!("❌ {e}");
+                return;
+            }
+            self.add_confirm = None;
+            key
+        } else if self.repo.config().path_map.contains_key(&entry) {
+            entry
+        } else {
+            self.status_msg = format!("Unknown scope '{entry}'. Use Key=Path to add it.");
+            return;
+        };
+
+        let scope = self.import_scopes[self.import_map.len()].clone();
+        self.import_map.insert(scope, key);
+        if self.import_map.len() < self.import_scopes.len() {
+            self.import_prompt_next_scope();
+            return;
+        }
+
+        self.input_mode = InputMode::Normal;
+        self.input.clear();
+        self.is_processing = true;
+        self.reload_needed = true;
+        self.status_msg = "Importing bundle...".into();
+
+        let (tx, rx) = mpsc::channel();
+        self.receiver = Some(rx);
+        let cancel = self.new_cancel_token();
+        let mut repo = self.repo.clone();
+        let path = self.import_path.clone();
+        let map = std::mem::take(&mut self.import_map);
+
+        thread::spawn(move || {
+            let outcome = repo.import(path, map, cancel, tx.clone());
+            send_outcome(repo.root(), &tx, outcome);
+        });
+    }
+
+    fn start_remote(&mut self, push: bool) {
+        if push {
+            let idx = self.state.selected().unwrap_or(0);
+            match self.ids.get(idx) {
+                Some(id) if !id.is_empty() => self.remote_target_id = id.clone(),
+                _ => return,
+            }
+        }
+
+        self.remote_push = push;
+        let last_remote = self
+            .repo
+            .config()
+            .remote
+            .as_ref()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default();
+        self.input.set(last_remote);
+        self.input_mode = InputMode::RemotePath;
+    }
+
+    fn action_remote(&mut self) {
+        let raw = self.input.text().trim().trim_matches('"').to_string();
+        if raw.is_empty() {
+            return;
+        }
+        let remote = PathBuf::from(raw);
+        if let Err(e) = self.repo.set_remote(remote.clone()) {
+            self.status_msg = format!("❌ {e}");
+            return;
+        }
+
+        self.input_mode = InputMode::Normal;
+        self.input.clear();
+        self.is_processing = true;
+
+        let (tx, rx) = mpsc::channel();
+        self.receiver = Some(rx);
+        let cancel = self.new_cancel_token();
+        let mut repo = self.repo.clone();
+
+        if self.remote_push {
+            self.status_msg = format!("Pushing '{}'...", self.remote_target_id);
+            let id = self.remote_target_id.clone();
+            thread::spawn(move || {
+                let outcome = repo.push(&id, remote, cancel, tx.clone());
+                send_outcome(repo.root(), &tx, outcome);
+            });
+        } else {
+            self.status_msg = "Pulling...".into();
+            self.reload_needed = true;
+            thread::spawn(move || {
+                let outcome = repo.pull(remote, cancel, tx.clone());
+                send_outcome(repo.root(), &tx, outcome);
+            });
+        }
+    }
+
+    /// Texto colado só vale nos campos de texto.
+    fn paste(&mut self, text: &str) {
+        match self.input_mode {
+            InputMode::Normal
+            | InputMode::Configuring
+            | InputMode::PathMap
+            | InputMode::Contents
+            | InputMode::Initializing => {}
+            InputMode::AddingPath => {
+                self.input.paste(text);
+                self.adding_path_edited();
+            }
+            InputMode::Searching => {
+                self.filter.paste(text);
+                self.apply_filter();
+            }
+            _ => self.input.paste(text),
+        }
+    }
+
+    fn new_cancel_token(&mut self) -> CancelToken {
+        let cancel = CancelToken::default();
+        self.cancel = Some(cancel.clone());
+        cancel
+    }
+
+    /// O engine para no próximo arquivo; o resultado chega como `Error(Cancelled)`.
+    fn request_cancel(&mut self) {
+        match &self.cancel {
+            Some(cancel) => {
+                cancel.cancel();
+                self.status_msg = "Cancelling...".into();
+            }
+            None => self.status_msg = "This operation can't be cancelled, waiting...".into(),
+        }
+    }
+
+    /// Durante a fase pesada (compressão, extração) a barra segue os bytes;
+    /// fora dela, o percentual da fase.
+    fn active_transfer(&self) -> Option<FileProgress> {
+        self.file_progress
+            .filter(|fp| fp.total_files > 0 && fp.files < fp.total_files)
+    }
 
-            let target = self.delete_target_id.clone();
-            let all: Vec<SetManifest> = self.manifests_cache.values().cloned().collect();
-            let (tx, rx) = mpsc::channel();
-            self.receiver = Some(rx);
-            let root = self.app_root.clone();
+    fn gauge_percent(&self) -> u16 {
+        match self.active_transfer() {
+            Some(fp) if fp.total_bytes > 0 => (fp.bytes * 100 / fp.total_bytes) as u16,
+            Some(fp) => (fp.files * 100 / fp.total_files) as u16,
+            None => self.progress,
+        }
+    }
 
-            thread::spawn(move || {
-                engine_delete_cascade(&root, target, &all, tx);
-            });
+    fn gauge_label(&self) -> String {
+        let fp = match self.active_transfer() {
+            Some(fp) => fp,
+            None => return format!("{}%", self.progress),
+        };
+        let mut label = format!(
+            "{}/{} files | {} / {}",
+            fp.files,
+            fp.total_files,
+            format_bytes(fp.bytes),
+            format_bytes(fp.total_bytes)
+        );
+        let secs = self.file_started.elapsed().as_secs_f64();
+        if secs >= 1.0 && fp.bytes > 0 {
+            let rate = fp.bytes as f64 / secs;
+            let eta = (fp.total_bytes.saturating_sub(fp.bytes)) as f64 / rate;
+            label.push_str(&format!(
+                " | {}/s | ETA {}",
+                format_bytes(rate as u64),
+                format_eta(eta as u64)
+            ));
         }
+        label
     }
 
     fn check_progress(&mut self) {
         let mut done = false;
+        let mut failed = false;
 
         if let Some(rx) = &self.receiver {
-            for (p, msg) in rx.try_iter() {
-                self.progress = p as u16;
-                self.status_msg = msg;
-                if self.progress >= 100 {
-                    done = true;
+            loop {
+                let event = match rx.try_recv() {
+                    Ok(ev) => ev,
+                    Err(mpsc::TryRecvError::Empty) => break,
+                    // Thread morreu sem Finished/Error (panic)
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        self.status_msg = "❌ Operation stopped unexpectedly".into();
+                        done = true;
+                        failed = true;
+                        break;
+                    }
+                };
+                match event {
+                    EngineEvent::Started { operation } => {
+                        self.op_name = operation;
+                        self.progress = 0;
+                        self.file_progress = None;
+                        self.op_warnings.clear();
+                    }
+                    EngineEvent::Phase { percent, label } => {
+                        self.progress = percent.clamp(0.0, 100.0) as u16;
+                        self.status_msg = label;
+                    }
+                    EngineEvent::FileProgress(fp) => {
+                        if fp.files == 0 {
+                            self.file_started = Instant::now();
+                        }
+                        self.file_progress = Some(fp);
+                    }
+                    EngineEvent::Warning(msg) => self.op_warnings.push(msg),
+                    EngineEvent::Error(e) => {
+                        self.status_msg = match e {
+                            BlkError::Cancelled => format!("⏹ {e}"),
+                            _ => format!("❌ {e}"),
+                        };
+                        for warning in &self.op_warnings {
+                            self.status_msg.push_str(&format!(" | ⚠ {warning}"));
+                        }
+                        done = true;
+                        failed = true;
+                        break;
+                    }
+                    EngineEvent::Finished { summary } => {
+                        self.status_msg = summary.to_string();
+                        for warning in &self.op_warnings {
+                            self.status_msg.push_str(&format!(" | ⚠ {warning}"));
+                        }
+                        done = true;
+                        break;
+                    }
                 }
             }
         }
 
         if done {
             if let InputMode::Initializing = self.input_mode {
-                *self = App::load_initial_state(self.app_root.clone());
-                self.input_mode = InputMode::Normal;
+                if failed {
+                    // Fica na tela de preparo mostrando o erro; [Q] sai
+                    self.is_processing = false;
+                    self.receiver = None;
+                    self.status_msg.push_str(" | [Q] Quit");
+                    return;
+                }
+                *self = App::load_initial_state(self.repo.root().to_path_buf());
                 return;
             }
 
             self.is_processing = false;
             self.progress = 0;
+            self.file_progress = None;
             self.receiver = None;
+            self.cancel = None;
 
             if self.reload_needed {
                 self.refresh_list();
+            } else {
+                // A tarefa rodou num clone do repo (a config do set antes de um save)
+                self.repo.reload_sets();
+            }
+
+            self.check_dir_status(false);
+        }
+
+        if let InputMode::AddingPath = self.input_mode {
+            self.probe_adding_path();
+        }
+        if let Some(rx) = &self.path_probe_receiver {
+            for probe in rx.try_iter() {
+                self.path_probe = Some(probe);
+            }
+        }
+
+        if let Some(rx) = &self.pathmap_receiver {
+            for status in rx.try_iter() {
+                self.pathmap_status.insert(status.key.clone(), status);
             }
+        }
 
-            self.check_dir_status();
+        let contents = self.contents_receiver.as_ref().and_then(|rx| rx.try_recv().ok());
+        if let Some(result) = contents {
+            self.contents_receiver = None;
+            self.contents_loaded(result);
         }
 
         if let Some(rx) = &self.diff_receiver {
-            for diff in rx.try_iter() {
-                self.diff_summary = diff;
-                self.status_msg = "Check completed.".into();
+            for result in rx.try_iter() {
+                match result {
+                    Ok(diff) => {
+                        self.diff_summary = diff;
+                        if self.announce_check {
+                            self.status_msg = "Check completed.".into();
+                        }
+                    }
+                    Err(e) => self.status_msg = format!("❌ Check failed: {e}"),
+                }
             }
         }
     }
@@ -672,6 +1834,16 @@ impl App {
 
 // ------------------------- UI helpers / main ------------------------------
 
+fn format_eta(secs: u64) -> String {
+    if secs < 60 {
+        format!("{secs}s")
+    } else if secs < 3600 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h{:02}m", secs / 3600, (secs % 3600) / 60)
+    }
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
@@ -702,13 +1874,13 @@ fn main() -> Result<(), io::Error> {
     let original_hook = panic::take_hook();
     panic::set_hook(Box::new(move |panic_info| {
         let _ = disable_raw_mode();
-        let _ = execute!(io::stdout(), LeaveAlternateScreen);
+        let _ = execute!(io::stdout(), LeaveAlternateScreen, DisableBracketedPaste);
         original_hook(panic_info);
     }));
 
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    execute!(stdout, EnterAlternateScreen, EnableBracketedPaste)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
     let mut app = App::new();
@@ -734,7 +1906,8 @@ fn main() -> Result<(), io::Error> {
                 );
                 let gauge = Gauge::default()
                     .gauge_style(Style::default().fg(Color::Green))
-                    .percent(app.progress);
+                    .percent(app.gauge_percent())
+                    .label(app.gauge_label());
                 f.render_widget(gauge, chunks[1]);
                 return;
             }
@@ -753,10 +1926,14 @@ fn main() -> Result<(), io::Error> {
                 .split(f.size());
 
             let root_display = app
-                .config
+                .repo
+                .config()
                 .path_map
                 .get("Root")
-                .map(|p| p.to_string_lossy())
+                .map(|p| match expand_path(p, app.repo.root()) {
+                    Ok(expanded) => expanded.to_string_lossy().to_string(),
+                    Err(e) => format!("⚠ {e}"),
+                })
                 .unwrap_or("?".into());
 
             let active_name = app
@@ -767,8 +1944,10 @@ fn main() -> Result<(), io::Error> {
                 .unwrap_or("None".into());
 
             let header = Paragraph::new(format!(
-                " -- BLK -- {} | Active: {}",
-                root_display, active_name
+                " -- BLK -- {} | Active: {} | Zip: {}",
+                root_display,
+                active_name,
+                app.repo.config().compression.profile.label()
             ))
             .style(Style::default().fg(Color::Black).bg(Color::White));
             f.render_widget(header, main_chunks[0]);
@@ -847,23 +2026,66 @@ fn main() -> Result<(), io::Error> {
                 left_panel_chunks[0],
             );
 
-            let list = List::new(
-                app.items
-                    .iter()
-                    .map(|i| ListItem::new(i.as_str()))
-                    .collect::<Vec<_>>(),
-            )
-            .block(
-                Block::default()
+            let searching = matches!(app.input_mode, InputMode::Searching);
+            let filtered = !app.filter.text().is_empty();
+            let list_chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints(if searching || filtered {
+                    [Constraint::Min(3), Constraint::Length(3)]
+                } else {
+                    [Constraint::Min(3), Constraint::Length(0)]
+                })
+                .split(left_panel_chunks[1]);
+
+            let mut list_items: Vec<ListItem> = app
+                .items
+                .iter()
+                .zip(&app.ids)
+                .map(|(name, id)| {
+                    let created = app
+                        .manifests_cache
+                        .get(id)
+                        .map(|m| format_created(m.created_at))
+                        .unwrap_or_default();
+                    ListItem::new(Line::from(vec![
+                        Span::raw(name.as_str()),
+                        Span::styled(format!("  {created}"), Style::default().fg(Color::DarkGray)),
+                    ]))
+                })
+                .collect();
+            if list_items.is_empty() {
+                list_items.push(ListItem::new(Span::styled(
+                    "No set matches the filter",
+                    Style::default().fg(Color::DarkGray),
+                )));
+            }
+            let list_title = if filtered {
+                format!(" Available Sets ({}/{}) ", app.items.len(), app.repo.sets().len())
+            } else {
+                " Available Sets ".to_string()
+            };
+            let list = List::new(list_items)
+                .block(Block::default().borders(Borders::ALL).title(list_title))
+                .highlight_style(
+                    Style::default()
+                        .bg(Color::Blue)
+                        .add_modifier(Modifier::BOLD),
+                );
+            f.render_stateful_widget(list, list_chunks[0], &mut app.state);
+
+            if searching || filtered {
+                let block = Block::default()
                     .borders(Borders::ALL)
-                    .title(" Available Sets "),
-            )
-            .highlight_style(
-                Style::default()
-                    .bg(Color::Blue)
-                    .add_modifier(Modifier::BOLD),
-            );
-            f.render_stateful_widget(list, left_panel_chunks[1], &mut app.state);
+                    .title(if searching { " Search " } else { " Search ([/] Edit, [Esc] Clear) " })
+                    .border_style(Style::default().fg(if searching { Color::Yellow } else { Color::DarkGray }));
+                let inner = block.inner(list_chunks[1]);
+                f.render_widget(block, list_chunks[1]);
+                if searching {
+                    app.filter.render(f, inner);
+                } else {
+                    f.render_widget(Paragraph::new(app.filter.text()), inner);
+                }
+            }
 
             let idx = app.state.selected().unwrap_or(0);
             let current_id = if idx < app.ids.len() {
@@ -888,7 +2110,7 @@ fn main() -> Result<(), io::Error> {
                             .get(node_id)
                             .map(|m| m.name.clone())
                             .unwrap_or("?".into());
-                        let size = get_snapshot_size(&app.app_root, node_id);
+                        let size = app.repo.snapshot_size(node_id);
                         let prefix = if i == 0 {
                             "".to_string()
                         } else {
@@ -914,8 +2136,15 @@ fn main() -> Result<(), io::Error> {
                         Style::default().fg(Color::Cyan),
                     )));
                     for scope in &man.scopes {
-                        let path_display = if let Some(p) = app.config.path_map.get(scope) {
-                            format!("{scope}: {}", p.to_string_lossy())
+                        let path_display = if let Some(p) = app.repo.config().path_map.get(scope) {
+                            let raw = p.to_string_lossy();
+                            match expand_path(p, app.repo.root()) {
+                                Ok(expanded) if expanded != *p => {
+                                    format!("{scope}: {raw} → {}", expanded.to_string_lossy())
+                                }
+                                Ok(_) => format!("{scope}: {raw}"),
+                                Err(e) => format!("{scope}: {raw} (⚠ {e})"),
+                            }
                         } else {
                             scope.clone()
                         };
@@ -958,8 +2187,8 @@ fn main() -> Result<(), io::Error> {
 
             if app.is_processing {
                 // CORREÇÃO: Feedback visual "ƒ" piscante
-                let blink_char = if (app.spinner_tick / 5) % 2 == 0 { "ƒ" } else { " " };
-                let title = format!(" Working... {} ", blink_char);
+                let blink_char = if (app.spinner_tick / 5).is_multiple_of(2) { "ƒ" } else { " " };
+                let title = format!(" Working: {}... {} ", app.op_name, blink_char);
 
                 let gauge = Gauge::default()
                     .block(
@@ -968,23 +2197,58 @@ fn main() -> Result<(), io::Error> {
                             .title(title),
                     )
                     .gauge_style(Style::default().fg(Color::Green))
-                    .percent(app.progress);
+                    .percent(app.gauge_percent())
+                    .label(app.gauge_label());
                 f.render_widget(gauge, main_chunks[2]);
             }
 
             let footer_content = match app.input_mode {
+                InputMode::Normal if app.is_processing => format!(
+                    "{} | [Esc] Cancel | [Q] Cancel & Quit",
+                    app.status_msg
+                ),
                 InputMode::Normal => format!(
-                    "{} | [Enter] Restore | [S] Save Delta | [D] Delete | [F5] Check | [Q] Quit",
+                    "{} | [Enter] Restore | [C] Contents | [S] Save Delta | [D] Delete | [X] Export | [I] Import | [P] Push | [L] Pull | [M] Path Map | [/] Search | [F5] Check | [Z] Zip | [Q] Quit",
                     app.status_msg
                 ),
+                InputMode::Searching => {
+                    "SEARCH: name, ID or date (e.g. 2024-05) | [↑↓] Move | [Enter] Keep Filter | [Esc] Clear".into()
+                }
                 InputMode::EditingName => {
                     "NEW DELTA NAME: type and [Enter], [Esc] cancels".into()
                 }
                 InputMode::ConfirmDelete => {
                     "DELETE: Type 'y' (simple) or 'DELETE' (cascade) and press [Enter]".into()
                 }
-                InputMode::Configuring => "CONFIG: [Tab] Switch | [A] Add (Name=Path or Path) | [D] Delete | [Enter] Save | [Esc] Close".into(),
-                InputMode::AddingPath => "PATH: Type and [Enter]. Ex: AC=C:\\Games\\Assetto  or  C:\\Users\\...".into(),
+                InputMode::Configuring => "CONFIG: [Tab] Switch | [A] Add (Key, Key=Path or Path) | [D] Delete | [Enter] Save | [Esc] Close".into(),
+                InputMode::AddingPath => match app.config_focus {
+                    ConfigFocus::Scopes => "SCOPE: existing Key, Key=Path or a folder path. [Tab] Complete | [Enter] Add | [Esc] Cancel".into(),
+                    ConfigFocus::Exclusions => "EXCLUSION: substring or glob and [Enter], [Esc] cancels".into(),
+                },
+                InputMode::ExportPath => {
+                    "EXPORT: [Enter] Export | [Tab] Full chain / Flattened | [Esc] Cancel".into()
+                }
+                InputMode::RemotePath => {
+                    "REMOTE: Path to the other BLK repository (.blk) and [Enter], [Esc] cancels".into()
+                }
+                InputMode::ImportPath => "IMPORT: Bundle path (.blkb) and [Enter], [Esc] cancels".into(),
+                InputMode::ImportScope => {
+                    format!("{} | SCOPE: existing Key or Key=Path and [Enter], [Esc] cancels", app.status_msg)
+                }
+                InputMode::PathMap => format!(
+                    "{} | PATH MAP: [E] Edit | [A] Add | [D] Remove | [Esc] Close",
+                    app.status_msg
+                ),
+                InputMode::Contents => {
+                    "CONTENTS: [↑↓] Move | [Enter/→] Open Folder | [←/Backspace] Up | [Tab] Own Layer / Reconstructed | [Esc] Close".into()
+                }
+                InputMode::EditingScopePath => format!(
+                    "{} | SCOPE PATH: type and [Enter], [Esc] cancels. Variables: ${{ROOT}} ${{HOME}} ${{DOCUMENTS}} ${{APPDATA}}",
+                    app.status_msg
+                ),
+                InputMode::ConfirmRemoveScope => {
+                    "REMOVE SCOPE: Type 'y' and press [Enter], [Esc] cancels".into()
+                }
                 InputMode::Initializing => "STARTUP...".into(),
             };
             f.render_widget(
@@ -999,14 +2263,62 @@ fn main() -> Result<(), io::Error> {
                 
                 let block = Block::default().borders(Borders::ALL).title("New Set");
                 let inner = block.inner(r);
-                f.render_widget(
-                    Paragraph::new(app.input_text.clone()).block(block),
-                    r,
-                );
-                f.set_cursor(
-                    inner.x + app.input_text.len() as u16,
-                    inner.y,
-                );
+                f.render_widget(block, r);
+                app.input.render(f, inner);
+            }
+
+            if let InputMode::ExportPath = app.input_mode {
+                let r = centered_rect(70, 20, f.size());
+                f.render_widget(Clear, r);
+
+                let mode = if app.export_flatten { "flattened" } else { "full chain" };
+                let block = Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("Export '{}' ({})", app.export_target_id, mode))
+                    .style(Style::default().fg(Color::Yellow));
+                let inner = block.inner(r);
+                f.render_widget(block, r);
+                app.input.render(f, inner);
+            }
+
+            if let InputMode::RemotePath = app.input_mode {
+                let r = centered_rect(70, 20, f.size());
+                f.render_widget(Clear, r);
+
+                let title = if app.remote_push {
+                    format!("Push '{}' (with its lineage) to", app.remote_target_id)
+                } else {
+                    "Pull missing sets from".to_string()
+                };
+                let block = Block::default()
+                    .borders(Borders::ALL)
+                    .title(title)
+                    .style(Style::default().fg(Color::Yellow));
+                let inner = block.inner(r);
+                f.render_widget(block, r);
+                app.input.render(f, inner);
+            }
+
+            if let InputMode::ImportPath | InputMode::ImportScope = app.input_mode {
+                let r = centered_rect(70, 20, f.size());
+                f.render_widget(Clear, r);
+
+                let title = match app.input_mode {
+                    InputMode::ImportScope => format!(
+                        "Map bundle scope '{}' ({}/{})",
+                        app.import_scopes[app.import_map.len()],
+                        app.import_map.len() + 1,
+                        app.import_scopes.len()
+                    ),
+                    _ => "Import bundle".to_string(),
+                };
+                let block = Block::default()
+                    .borders(Borders::ALL)
+                    .title(title)
+                    .style(Style::default().fg(Color::Yellow));
+                let inner = block.inner(r);
+                f.render_widget(block, r);
+                app.input.render(f, inner);
             }
 
             if let InputMode::ConfirmDelete = app.input_mode {
@@ -1026,15 +2338,9 @@ fn main() -> Result<(), io::Error> {
                     Paragraph::new(app.delete_warning_msg.clone()).wrap(Wrap { trim: true }),
                     c[0],
                 );
-                f.render_widget(
-                    Paragraph::new(app.input_text.clone())
-                        .block(Block::default().borders(Borders::ALL)),
-                    c[1],
-                );
-                f.set_cursor(
-                     c[1].x + 1 + app.input_text.len() as u16,
-                     c[1].y + 1
-                );
+                let field = Block::default().borders(Borders::ALL);
+                f.render_widget(field.clone(), c[1]);
+                app.input.render(f, field.inner(c[1]));
             }
 
             if let InputMode::Configuring | InputMode::AddingPath = app.input_mode {
@@ -1102,31 +2408,272 @@ fn main() -> Result<(), io::Error> {
                 }
 
                 if let InputMode::AddingPath = app.input_mode {
-                    let ir2 = centered_rect(60, 15, r);
+                    let ir2 = centered_rect(70, 40, r);
                     f.render_widget(Clear, ir2);
-                    
+
+                    let title = match app.config_focus {
+                        ConfigFocus::Scopes => "Add scope (Key, Key=Path or Path)",
+                        ConfigFocus::Exclusions => "Add exclusion",
+                    };
                     let block = Block::default()
                         .borders(Borders::ALL)
-                        .title("Add Path (Name=Path) or raw Path")
+                        .title(title)
                         .style(Style::default().fg(Color::Yellow));
                     let inner = block.inner(ir2);
+                    f.render_widget(block, ir2);
+
+                    let c = Layout::default()
+                        .constraints([Constraint::Length(1), Constraint::Length(1), Constraint::Min(1)])
+                        .split(inner);
+                    app.input.render(f, c[0]);
+
+                    if let Some((hint, color)) = app.adding_path_hint() {
+                        f.render_widget(
+                            Paragraph::new(hint).style(Style::default().fg(color)),
+                            c[1],
+                        );
+                    }
+
+                    let mut lines = Vec::new();
+                    if !app.add_message.is_empty() {
+                        lines.push(Line::from(Span::styled(
+                            app.add_message.clone(),
+                            Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+                        )));
+                    }
+                    for candidate in &app.completions {
+                        let name = candidate
+                            .trim_end_matches(['/', '\\'])
+                            .rsplit(['/', '\\'])
+                            .next()
+                            .unwrap_or(candidate);
+                        lines.push(Line::from(Span::styled(
+                            format!("  {name}"),
+                            Style::default().fg(Color::DarkGray),
+                        )));
+                    }
+                    f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), c[2]);
+                }
+            }
+
+            if let InputMode::PathMap | InputMode::EditingScopePath | InputMode::ConfirmRemoveScope =
+                app.input_mode
+            {
+                let r = centered_rect(80, 80, f.size());
+                f.render_widget(Clear, r);
+
+                let items: Vec<ListItem> = app
+                    .pathmap_keys
+                    .iter()
+                    .map(|key| {
+                        let raw = app.repo.config().path_map[key].to_string_lossy().to_string();
+                        let mut head = vec![Span::styled(
+                            format!("{key}: "),
+                            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                        )];
+                        head.push(Span::raw(raw.clone()));
+
+                        let (state, color) = match app.pathmap_status.get(key) {
+                            None => ("… measuring".to_string(), Color::DarkGray),
+                            Some(st) => match &st.resolved {
+                                Err(e) => (format!("⚠ {e}"), Color::Red),
+                                Ok(resolved) => {
+                                    if resolved.to_string_lossy() != raw {
+                                        head.push(Span::styled(
+                                            format!(" → {}", resolved.to_string_lossy()),
+                                            Style::default().fg(Color::DarkGray),
+                                        ));
+                                    }
+                                    if !st.exists {
+                                        ("✖ Missing".to_string(), Color::Red)
+                                    } else if !st.is_dir {
+                                        (format!("⚠ Not a folder ({})", format_bytes(st.bytes)), Color::Yellow)
+                                    } else {
+                                        (
+                                            format!("✔ {} files, {}", st.files, format_bytes(st.bytes)),
+                                            Color::Green,
+                                        )
+                                    }
+                                }
+                            },
+                        };
+                        let users = app.repo.sets_using_scope(key).len();
+                        ListItem::new(vec![
+                            Line::from(head),
+                            Line::from(vec![
+                                Span::raw("   "),
+                                Span::styled(state, Style::default().fg(color)),
+                                Span::styled(
+                                    format!(" | used by {users} set(s)"),
+                                    Style::default().fg(Color::DarkGray),
+                                ),
+                            ]),
+                        ])
+                    })
+                    .collect();
 
+                let block = Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!(" Path Map ({} scopes) ", app.pathmap_keys.len()));
+                if items.is_empty() {
                     f.render_widget(
-                        Paragraph::new(app.input_text.clone()).block(block),
-                        ir2,
+                        Paragraph::new("No scopes mapped. [A] adds one.").block(block),
+                        r,
                     );
+                } else {
+                    let list = List::new(items)
+                        .block(block)
+                        .highlight_style(Style::default().bg(Color::DarkGray));
+                    f.render_stateful_widget(list, r, &mut app.pathmap_state);
+                }
+
+                if let InputMode::EditingScopePath = app.input_mode {
+                    let ir = centered_rect(60, 15, r);
+                    f.render_widget(Clear, ir);
+
+                    let title = match &app.pathmap_edit_key {
+                        Some(key) => format!("Path for '{key}'"),
+                        None => "Add scope (Key=Path)".to_string(),
+                    };
+                    let block = Block::default()
+                        .borders(Borders::ALL)
+                        .title(title)
+                        .style(Style::default().fg(Color::Yellow));
+                    let inner = block.inner(ir);
+                    f.render_widget(block, ir);
+                    app.input.render(f, inner);
+                }
 
-                    // Cursor seguro (não estoura borda)
-                    let max_width = inner.width.saturating_sub(1);
-                    let len = app.input_text.len() as u16;
-                    let cursor_x = inner.x + len.min(max_width);
-                    let cursor_y = inner.y;
-                    f.set_cursor(cursor_x, cursor_y);
+                if let InputMode::ConfirmRemoveScope = app.input_mode {
+                    let ir = centered_rect(60, 40, r);
+                    f.render_widget(Clear, ir);
+                    let b = Block::default()
+                        .borders(Borders::ALL)
+                        .title("REMOVE SCOPE")
+                        .style(Style::default().fg(Color::Red));
+                    let i = b.inner(ir);
+                    f.render_widget(b, ir);
+
+                    let c = Layout::default()
+                        .constraints([Constraint::Min(4), Constraint::Length(3)])
+                        .split(i);
+                    f.render_widget(
+                        Paragraph::new(app.pathmap_remove_msg.clone()).wrap(Wrap { trim: true }),
+                        c[0],
+                    );
+                    let field = Block::default().borders(Borders::ALL);
+                    f.render_widget(field.clone(), c[1]);
+                    app.input.render(f, field.inner(c[1]));
                 }
             }
+
+            if let InputMode::Contents = app.input_mode {
+                let r = centered_rect(90, 85, f.size());
+                f.render_widget(Clear, r);
+
+                let set_name = |id: &str| {
+                    app.manifests_cache
+                        .get(id)
+                        .map_or_else(|| id.to_string(), |m| m.name.clone())
+                };
+                let view = if app.contents_full { "reconstructed" } else { "own layer" };
+                let block = Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!(" Contents: {} ({view}) ", set_name(&app.contents_id)));
+                let inner = block.inner(r);
+                f.render_widget(block, r);
+
+                // Coluna de origem do tamanho do maior nome da cadeia
+                let source_width = app
+                    .repo
+                    .chain(&app.contents_id)
+                    .iter()
+                    .map(|m| m.name.chars().count())
+                    .max()
+                    .unwrap_or(0)
+                    .clamp(14, 24);
+
+                let c = Layout::default()
+                    .constraints([Constraint::Length(3), Constraint::Min(1)])
+                    .split(inner);
+                f.render_widget(
+                    Paragraph::new(vec![
+                        Line::from(Span::styled(
+                            format!("/{}", app.contents_dir),
+                            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                        )),
+                        Line::from(Span::styled(
+                            app.contents_summary.clone(),
+                            Style::default().fg(Color::DarkGray),
+                        )),
+                        Line::from(Span::styled(
+                            contents_header(source_width),
+                            Style::default().fg(Color::Yellow),
+                        )),
+                    ]),
+                    c[0],
+                );
+                let items: Vec<ListItem> = app
+                    .contents_rows
+                    .iter()
+                    .map(|row| {
+                        let size = if row.deleted {
+                            "deleted".to_string()
+                        } else {
+                            format_bytes(row.size)
+                        };
+                        let detail = match row.kind {
+                            _ if row.deleted => "-".to_string(),
+                            EntryKind::Dir => format!("{} files", row.files),
+                            EntryKind::Symlink => "link".to_string(),
+                            EntryKind::File => format!("{:08x}", row.crc32),
+                        };
+                        let source = if row.mixed {
+                            "(several sets)".to_string()
+                        } else {
+                            set_name(&row.source)
+                        };
+                        let source: String = source.chars().take(source_width).collect();
+                        let source_color = if row.source == app.contents_id && !row.mixed {
+                            Color::Green
+                        } else {
+                            Color::DarkGray
+                        };
+                        let (name, name_style) = match row.kind {
+                            EntryKind::Dir => (
+                                format!("{}/", row.name),
+                                Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                            ),
+                            _ => (row.name.clone(), Style::default()),
+                        };
+                        let name_style = if row.deleted {
+                            name_style.fg(Color::Red).add_modifier(Modifier::CROSSED_OUT)
+                        } else {
+                            name_style
+                        };
+                        ListItem::new(Line::from(vec![
+                            Span::styled(
+                                format!("{size:>10}  {detail:<12}"),
+                                Style::default().fg(if row.deleted { Color::Red } else { Color::White }),
+                            ),
+                            Span::styled(
+                                format!("{source:<source_width$}  "),
+                                Style::default().fg(source_color),
+                            ),
+                            Span::styled(name, name_style),
+                        ]))
+                    })
+                    .collect();
+
+                let list = List::new(items).highlight_style(Style::default().bg(Color::DarkGray));
+                f.render_stateful_widget(list, c[1], &mut app.contents_state);
+            }
         })?;
 
         app.check_progress();
+        if app.quit_after_op && !app.is_processing {
+            break;
+        }
 
         // Incrementa o contador de tick para o spinner piscar
         app.spinner_tick = app.spinner_tick.wrapping_add(1);
@@ -1138,20 +2685,27 @@ fn main() -> Result<(), io::Error> {
         }
 
         if event::poll(Duration::from_millis(50))? {
-            if let Event::Key(key) = event::read()? {
+            let ev = event::read()?;
+            if let Event::Paste(text) = &ev {
+                app.paste(text);
+            }
+            if let Event::Key(key) = ev {
                 if key.kind == KeyEventKind::Press {
                     match app.input_mode {
                         InputMode::Normal => match key.code {
+                            KeyCode::Char('q') if app.is_processing => {
+                                app.quit_after_op = true;
+                                app.request_cancel();
+                            }
                             KeyCode::Char('q') => break,
-                            KeyCode::Down => {
-                                if !app.is_processing {
-                                    app.next();
-                                }
+                            KeyCode::Esc if app.is_processing => app.request_cancel(),
+                            KeyCode::Esc if !app.filter.text().is_empty() => app.clear_filter(),
+                            KeyCode::Char('/') if !app.is_processing => app.start_search(),
+                            KeyCode::Down if !app.is_processing => {
+                                app.next();
                             }
-                            KeyCode::Up => {
-                                if !app.is_processing {
-                                    app.previous();
-                                }
+                            KeyCode::Up if !app.is_processing => {
+                                app.previous();
                             }
                             KeyCode::PageDown => {
                                 app.tree_scroll = app.tree_scroll.saturating_add(3);
@@ -1163,50 +2717,66 @@ fn main() -> Result<(), io::Error> {
                                     app.tree_scroll = 0;
                                 }
                             }
-                            KeyCode::Enter => {
-                                if !app.is_processing {
-                                    app.action_restore();
-                                }
+                            KeyCode::Enter if !app.is_processing => {
+                                app.action_restore();
                             }
-                            KeyCode::Char('s') => {
-                                if !app.is_processing {
-                                    app.pending_save_after_config = true;
-                                    app.start_config_viewer();
-                                }
+                            KeyCode::Char('s') if !app.is_processing => {
+                                app.pending_save_after_config = true;
+                                app.start_config_viewer();
                             }
-                            KeyCode::Char('d') => {
-                                if !app.is_processing {
-                                    app.start_delete_process();
-                                }
+                            KeyCode::Char('d') if !app.is_processing => {
+                                app.start_delete_process();
                             }
-                            KeyCode::F(5) => {
-                                if !app.is_processing {
-                                    app.check_dir_status();
-                                }
+                            KeyCode::Char('x') if !app.is_processing => {
+                                app.start_export();
+                            }
+                            KeyCode::Char('i') if !app.is_processing => {
+                                app.start_import();
+                            }
+                            KeyCode::Char('p') if !app.is_processing => {
+                                app.start_remote(true);
+                            }
+                            KeyCode::Char('l') if !app.is_processing => {
+                                app.start_remote(false);
+                            }
+                            KeyCode::Char('z') if !app.is_processing => {
+                                app.cycle_compression_profile();
+                            }
+                            KeyCode::Char('m') if !app.is_processing => {
+                                app.open_path_map();
+                            }
+                            KeyCode::Char('c') if !app.is_processing => {
+                                app.open_contents();
+                            }
+                            KeyCode::F(5) if !app.is_processing => {
+                                app.check_dir_status(true);
                             }
                             _ => {}
                         },
+                        InputMode::Searching => match key.code {
+                            KeyCode::Esc => app.clear_filter(),
+                            KeyCode::Enter => app.input_mode = InputMode::Normal,
+                            KeyCode::Down => app.next(),
+                            KeyCode::Up => app.previous(),
+                            _ => {
+                                if app.filter.handle_key(key) {
+                                    app.apply_filter();
+                                }
+                            }
+                        },
                         InputMode::EditingName => match key.code {
                             KeyCode::Esc => app.input_mode = InputMode::Normal,
                             KeyCode::Enter => app.action_save(),
-                            KeyCode::Backspace => {
-                                app.input_text.pop();
-                            }
-                            KeyCode::Char(c) => {
-                                app.input_text.push(c);
+                            _ => {
+                                app.input.handle_key(key);
                             }
-                            _ => {}
                         },
                         InputMode::ConfirmDelete => match key.code {
                             KeyCode::Esc => app.input_mode = InputMode::Normal,
                             KeyCode::Enter => app.action_delete_confirm(),
-                            KeyCode::Backspace => {
-                                app.input_text.pop();
-                            }
-                            KeyCode::Char(c) => {
-                                app.input_text.push(c);
+                            _ => {
+                                app.input.handle_key(key);
                             }
-                            _ => {}
                         },
                         InputMode::Configuring => match key.code {
                             KeyCode::Esc => app.input_mode = InputMode::Normal,
@@ -1220,15 +2790,80 @@ fn main() -> Result<(), io::Error> {
                         InputMode::AddingPath => match key.code {
                             KeyCode::Esc => app.input_mode = InputMode::Configuring,
                             KeyCode::Enter => app.config_confirm_add(),
-                            KeyCode::Backspace => {
-                                app.input_text.pop();
+                            KeyCode::Tab => app.complete_adding_path(),
+                            _ => {
+                                if app.input.handle_key(key) {
+                                    app.adding_path_edited();
+                                }
+                            }
+                        },
+                        InputMode::ExportPath => match key.code {
+                            KeyCode::Esc => app.input_mode = InputMode::Normal,
+                            KeyCode::Enter => app.action_export(),
+                            KeyCode::Tab => app.export_toggle_flatten(),
+                            _ => {
+                                app.input.handle_key(key);
+                            }
+                        },
+                        InputMode::RemotePath => match key.code {
+                            KeyCode::Esc => app.input_mode = InputMode::Normal,
+                            KeyCode::Enter => app.action_remote(),
+                            _ => {
+                                app.input.handle_key(key);
+                            }
+                        },
+                        InputMode::ImportPath => match key.code {
+                            KeyCode::Esc => app.input_mode = InputMode::Normal,
+                            KeyCode::Enter => app.import_confirm_path(),
+                            _ => {
+                                app.input.handle_key(key);
                             }
-                            KeyCode::Char(c) => {
-                                app.input_text.push(c);
+                        },
+                        InputMode::ImportScope => match key.code {
+                            KeyCode::Esc => app.input_mode = InputMode::Normal,
+                            KeyCode::Enter => app.import_confirm_scope(),
+                            _ => {
+                                app.input.handle_key(key);
                             }
+                        },
+                        InputMode::Contents => match key.code {
+                            KeyCode::Esc => app.close_contents(),
+                            KeyCode::Down => app.contents_move(true),
+                            KeyCode::Up => app.contents_move(false),
+                            KeyCode::Enter | KeyCode::Right => app.contents_open(),
+                            KeyCode::Left | KeyCode::Backspace => app.contents_up(),
+                            KeyCode::Tab => app.contents_toggle_full(),
                             _ => {}
                         },
-                        InputMode::Initializing => {}
+                        InputMode::PathMap => match key.code {
+                            KeyCode::Esc => app.close_path_map(),
+                            KeyCode::Down => app.pathmap_move(true),
+                            KeyCode::Up => app.pathmap_move(false),
+                            KeyCode::Char('e') | KeyCode::Enter => app.pathmap_start_edit(),
+                            KeyCode::Char('a') => app.pathmap_start_add(),
+                            KeyCode::Char('d') => app.pathmap_start_remove(),
+                            _ => {}
+                        },
+                        InputMode::EditingScopePath => match key.code {
+                            KeyCode::Esc => app.input_mode = InputMode::PathMap,
+                            KeyCode::Enter => app.pathmap_confirm_edit(),
+                            _ => {
+                                app.input.handle_key(key);
+                            }
+                        },
+                        InputMode::ConfirmRemoveScope => match key.code {
+                            KeyCode::Esc => app.input_mode = InputMode::PathMap,
+                            KeyCode::Enter => app.pathmap_confirm_remove(),
+                            _ => {
+                                app.input.handle_key(key);
+                            }
+                        },
+                        InputMode::Initializing => {
+                            // Só depois de um init que falhou
+                            if key.code == KeyCode::Char('q') && !app.is_processing {
+                                break;
+                            }
+                        }
                     }
                 }
             }
@@ -1236,6 +2871,187 @@ fn main() -> Result<(), io::Error> {
     }
 
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableBracketedPaste)?;
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Repositório novo (só o vanilla) e a App aberta sobre ele
+    fn app_with(files: &[(&str, &str)]) -> (tempfile::TempDir, App) {
+        let dir = tempfile::tempdir().unwrap();
+        for (rel, body) in files {
+            let path = dir.path().join(rel);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, body).unwrap();
+        }
+        Repository::init(dir.path(), mpsc::channel().0).unwrap();
+        let app = App::load_initial_state(dir.path().to_path_buf());
+        (dir, app)
+    }
+
+    #[test]
+    fn import_scope_answer_goes_through_the_path_checks() {
+        let (dir, mut app) = app_with(&[("game.txt", "base")]);
+        app.import_scopes = vec!["Mods".into(), "Saves".into()];
+        app.import_prompt_next_scope();
+
+        // Pasta inexistente: só entra no segundo [Enter]
+        app.input.set("Mods=${ROOT}/nope");
+        app.import_confirm_scope();
+        assert!(app.status_msg.contains("doesn't exist"), "{}", app.status_msg);
+        assert!(!app.repo.config().path_map.contains_key("Mods"));
+        app.import_confirm_scope();
+        assert!(app.repo.config().path_map.contains_key("Mods"));
+        assert_eq!(app.import_map.get("Mods").map(String::as_str), Some("Mods"));
+
+        // Chave já mapeada não é trocada em silêncio; arquivo nunca vira scope
+        app.input.set("Root=${ROOT}/game.txt");
+        app.import_confirm_scope();
+        assert!(app.status_msg.contains("is a file"), "{}", app.status_msg);
+        std::fs::create_dir(dir.path().join("other")).unwrap();
+        app.input.set("Root=${ROOT}/other");
+        app.import_confirm_scope();
+        assert!(app.status_msg.contains("already maps"), "{}", app.status_msg);
+        assert_eq!(app.repo.config().path_map["Root"], Path::new("${ROOT}"));
+        assert_eq!(app.import_map.len(), 1);
+    }
+
+    #[test]
+    fn scope_entry_is_a_known_key_a_mapping_or_a_folder_path() {
+        let (_dir, app) = app_with(&[]);
+        let path = |key: &str, path: &str| ScopeEntry::Path { key: key.into(), path: path.into() };
+
+        assert_eq!(app.parse_scope_entry("Root"), Ok(ScopeEntry::Key("Root".into())));
+        assert_eq!(app.parse_scope_entry("Mods = \"${ROOT}/mods/\""), Ok(path("Mods", "${ROOT}/mods")));
+        assert_eq!(app.parse_scope_entry("${HOME}/My Saves/"), Ok(path("My_Saves", "${HOME}/My Saves")));
+        // Palavra solta não vira scope novo por engano
+        assert!(app.parse_scope_entry("mods").unwrap_err().contains("Unknown scope 'mods'"));
+        assert!(app.parse_scope_entry("=/x").is_err());
+        assert!(app.parse_scope_entry("Mods=").is_err());
+    }
+
+    #[test]
+    fn tab_completes_keys_for_a_word_and_folders_for_a_path() {
+        let (_dir, mut app) = app_with(&[("content/cars/a.txt", "a"), ("content/tracks/b.txt", "b")]);
+        app.repo.set_path("Rally", "${ROOT}/content").unwrap();
+        app.config_focus = ConfigFocus::Scopes;
+
+        app.input.set("ra");
+        app.complete_adding_path();
+        assert_eq!(app.input.text(), "Rally");
+
+        app.input.set("R");
+        app.complete_adding_path();
+        assert_eq!(app.completions, ["Rally", "Root"]);
+
+        app.input.set("Cars=${ROOT}/content/c");
+        app.complete_adding_path();
+        assert_eq!(app.input.text(), "Cars=${ROOT}/content/cars/");
+
+        app.input.set("./nothing_here");
+        app.complete_adding_path();
+        assert_eq!(app.add_message, "No matches");
+    }
+
+    #[test]
+    fn filter_keeps_items_ids_and_selection_in_step() {
+        let (dir, _) = app_with(&[]);
+        // 2024-05-12 12:00 UTC: o mesmo mês em qualquer fuso
+        for (id, name, created_at) in [("rally_pack", "Rally Pack", 1_715_515_200), ("drift", "Drift", 0)] {
+            let man = SetManifest {
+                id: id.into(),
+                name: name.into(),
+                parent_id: Some("vanilla".into()),
+                scopes: vec!["Root".into()],
+                exclusions: vec![],
+                created_at,
+                deleted_paths: vec![],
+            };
+            let path = dir.path().join(format!(".blk/sets/{id}.json"));
+            std::fs::write(path, serde_json::to_string(&man).unwrap()).unwrap();
+        }
+        let mut app = App::load_initial_state(dir.path().to_path_buf());
+        let selected = |app: &App| app.state.selected().map(|i| (app.items[i].clone(), app.ids[i].clone()));
+        let rally = Some(("Rally Pack".to_string(), "rally_pack".to_string()));
+        app.state.select(app.ids.iter().position(|id| id == "rally_pack"));
+
+        for filter in ["PACK", "rally_", "2024-05", "rally 2024"] {
+            app.filter.set(filter);
+            app.apply_filter();
+            assert_eq!(app.ids, ["rally_pack"], "{filter}");
+            assert_eq!(selected(&app), rally, "{filter}");
+        }
+
+        // Sem filtro a lista volta inteira e a seleção segue no mesmo set
+        app.filter.set("");
+        app.apply_filter();
+        assert_eq!((app.items.len(), app.ids.len()), (3, 3));
+        assert_eq!(selected(&app), rally);
+
+        // Nada casa: lista vazia, sem o "BLK Zero Set" de repositório vazio
+        app.filter.set("zzz");
+        app.apply_filter();
+        assert!(app.items.is_empty() && app.ids.is_empty());
+        assert_eq!(app.state.selected(), None);
+    }
+
+    #[test]
+    fn only_finished_or_error_ends_an_operation() {
+        let (_dir, mut app) = app_with(&[]);
+        let (tx, rx) = mpsc::channel();
+        app.receiver = Some(rx);
+        app.is_processing = true;
+
+        // 100% numa fase não é fim: só Finished/Error encerram
+        tx.send(EngineEvent::Started { operation: "save" }).unwrap();
+        tx.send(EngineEvent::phase(100.0, "Writing...")).unwrap();
+        app.check_progress();
+        assert!(app.is_processing);
+        assert_eq!(app.status_msg, "Writing...");
+
+        tx.send(EngineEvent::Warning("slow disk".into())).unwrap();
+        tx.send(EngineEvent::Error(BlkError::Integrity("bad layer".into()))).unwrap();
+        app.check_progress();
+        assert!(!app.is_processing);
+        assert!(app.status_msg.starts_with("❌"), "{}", app.status_msg);
+        assert!(app.status_msg.ends_with("| ⚠ slow disk"), "{}", app.status_msg);
+    }
+
+    fn entry(path: &str, kind: EntryKind, size: u64, source: &str, deleted: bool) -> ContentEntry {
+        ContentEntry {
+            path: path.into(),
+            kind,
+            size,
+            crc32: 0xdeadbeef,
+            source: source.into(),
+            deleted,
+        }
+    }
+
+    #[test]
+    fn contents_rows_total_folders_and_hide_deletions_in_the_full_view() {
+        let entries = [
+            entry("Root/mods", EntryKind::Dir, 0, "base", false),
+            entry("Root/mods/a.pak", EntryKind::File, 10, "base", false),
+            entry("Root/mods/b.pak", EntryKind::File, 5, "child", false),
+            entry("Root/old", EntryKind::File, 1, "child", true),
+            entry("Root/game.exe", EntryKind::File, 7, "base", false),
+        ];
+
+        let rows = content_rows(&entries, "Root", false);
+        let names: Vec<&str> = rows.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["mods", "old", "game.exe"]);
+        assert_eq!((rows[0].files, rows[0].size, rows[0].mixed), (2, 15, true));
+        assert!(rows[1].deleted);
+        assert_eq!(rows[2].crc32, 0xdeadbeef);
+
+        let full = content_rows(&entries, "Root", true);
+        assert!(full.iter().all(|r| r.name != "old"));
+        assert_eq!(content_rows(&entries, "", true)[0].name, "Root");
+
+        // O cabeçalho chama de CRC32 a coluna onde o checksum aparece
+        assert_eq!(contents_header(14).find("CRC32"), Some(12));
+    }
+}
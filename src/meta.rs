@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::{BlkError, BlkResult, Context};
+use crate::models::{BlkConfig, FileEntry, RepoFormat, SetManifest};
+
+// -----------------------------------------------------------------------------
+// Metadados em `.blk`
+// -----------------------------------------------------------------------------
+//
+// Todo JSON do repositório (config, manifestos, baseline, índices) passa por
+// aqui. Leitura: arquivo ausente tem significado próprio (config padrão, set
+// inexistente), mas JSON corrompido é sempre erro. Escrita: arquivo temporário
+// + rename, então quem lê nunca vê um JSON pela metade.
+
+pub(crate) fn blk_dir(root: &Path) -> PathBuf {
+    root.join(".blk")
+}
+
+pub(crate) fn config_path(root: &Path) -> PathBuf {
+    blk_dir(root).join("config.json")
+}
+
+pub(crate) fn sets_dir(root: &Path) -> PathBuf {
+    blk_dir(root).join("sets")
+}
+
+pub(crate) fn manifest_path(root: &Path, id: &str) -> PathBuf {
+    sets_dir(root).join(format!("{id}.json"))
+}
+
+fn format_path(root: &Path) -> PathBuf {
+    blk_dir(root).join("format.json")
+}
+
+fn baseline_path(root: &Path) -> PathBuf {
+    blk_dir(root).join("baseline.json")
+}
+
+pub(crate) fn write_atomic(path: &Path, bytes: &[u8]) -> BlkResult<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let written = File::create(&tmp)
+        .and_then(|mut file| {
+            file.write_all(bytes)?;
+            file.sync_all()
+        })
+        .at("write", &tmp)
+        .and_then(|_| fs::rename(&tmp, path).at("replace", path));
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    written
+}
+
+pub(crate) fn write_json<T: Serialize>(path: &Path, value: &T) -> BlkResult<()> {
+    let json = serde_json::to_string_pretty(value).at("encode", path)?;
+    write_atomic(path, json.as_bytes())
+}
+
+/// `None` se o arquivo não existe; erro se existe mas não é lido ou interpretado.
+pub(crate) fn read_json<T: DeserializeOwned>(path: &Path) -> BlkResult<Option<T>> {
+    let txt = match fs::read_to_string(path) {
+        Ok(txt) => txt,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).at("read", path),
+    };
+    serde_json::from_str(&txt).at("parse", path).map(Some)
+}
+
+// ------------------------------- format --------------------------------------
+
+/// `None` para repositórios criados antes do `format.json`.
+pub(crate) fn load_format(root: &Path) -> BlkResult<Option<RepoFormat>> {
+    read_json(&format_path(root))
+}
+
+pub(crate) fn save_format(root: &Path, format: &RepoFormat) -> BlkResult<()> {
+    write_json(&format_path(root), format)
+}
+
+// ------------------------------- config --------------------------------------
+
+// Nome de scope vira pasta dentro do zip ("Scope/rel") e prefixo no baseline ("scope::rel")
+fn validate_config(config: &BlkConfig) -> BlkResult<()> {
+    for (key, path) in &config.path_map {
+        if key.trim().is_empty() || key.contains(['/', '\\']) || key.contains("::") {
+            return Err(BlkError::Config(format!(
+                "Invalid scope name '{key}': it can't be empty or contain '/', '\\' or '::'"
+            )));
+        }
+        if path.as_os_str().is_empty() {
+            return Err(BlkError::Config(format!("Scope '{key}' has an empty path")));
+        }
+    }
+    Ok(())
+}
+
+/// Sem `config.json` vale a config padrão; um arquivo ilegível é erro.
+pub(crate) fn load_config(root: &Path) -> BlkResult<BlkConfig> {
+    let config = read_json(&config_path(root))?.unwrap_or_default();
+    validate_config(&config)?;
+    Ok(config)
+}
+
+pub(crate) fn save_config(root: &Path, config: &BlkConfig) -> BlkResult<()> {
+    validate_config(config)?;
+    write_json(&config_path(root), config)
+}
+
+/// Lê-altera-grava; quem chama segura o `RepoLock`.
+pub(crate) fn update_config(root: &Path, change: impl FnOnce(&mut BlkConfig)) -> BlkResult<()> {
+    let mut config = load_config(root)?;
+    change(&mut config);
+    save_config(root, &config)
+}
+
+// ------------------------------ manifests ------------------------------------
+
+/// Um id vira nome de arquivo (`sets/{id}.json`, `snapshots/{id}.zip`), então
+/// não pode ter separador nem ser `.`/`..`.
+// Nomes que o Windows reserva com qualquer extensão ("con.zip" também)
+const RESERVED_NAMES: [&str; 4] = ["CON", "PRN", "AUX", "NUL"];
+
+/// O id vira nome de arquivo (`<id>.zip`, `sets/<id>.json`) em qualquer SO:
+/// só `[A-Za-z0-9._-]`, e nada de nomes reservados do Windows.
+fn bad_id(id: &str) -> bool {
+    let stem = id.split('.').next().unwrap_or_default().to_ascii_uppercase();
+    let device = matches!(
+        stem.as_bytes(),
+        [b'C', b'O', b'M', b'1'..=b'9'] | [b'L', b'P', b'T', b'1'..=b'9']
+    );
+    id.is_empty()
+        || id == "."
+        || id == ".."
+        || !id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+        || RESERVED_NAMES.contains(&stem.as_str())
+        || device
+}
+
+/// Regras de id comuns ao manifesto no disco e aos sets de um bundle.
+pub(crate) fn validate_set_ids(origin: &str, man: &SetManifest) -> BlkResult<()> {
+    let invalid = |why: String| Err(BlkError::Integrity(format!("{origin}: {why}")));
+    if bad_id(&man.id) {
+        return invalid(format!("invalid set id '{}'", man.id));
+    }
+    match man.parent_id.as_deref() {
+        Some(parent) if bad_id(parent) => {
+            return invalid(format!("invalid parent id '{parent}' for set '{}'", man.id));
+        }
+        Some(parent) if parent == man.id => {
+            return invalid(format!("set '{}' is its own parent", man.id));
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn validate_manifest(path: &Path, man: &SetManifest) -> BlkResult<()> {
+    let origin = path.to_string_lossy();
+    validate_set_ids(&origin, man)?;
+    if path.file_stem().is_some_and(|stem| stem != man.id.as_str()) {
+        return Err(BlkError::Integrity(format!(
+            "{origin}: set id '{}' doesn't match the file name",
+            man.id
+        )));
+    }
+    Ok(())
+}
+
+/// `None` se o set não existe.
+pub(crate) fn load_manifest(root: &Path, id: &str) -> BlkResult<Option<SetManifest>> {
+    let path = manifest_path(root, id);
+    let man = read_json(&path)?;
+    if let Some(man) = &man {
+        validate_manifest(&path, man)?;
+    }
+    Ok(man)
+}
+
+pub(crate) fn save_manifest(root: &Path, man: &SetManifest) -> BlkResult<()> {
+    let path = manifest_path(root, &man.id);
+    validate_manifest(&path, man)?;
+    write_json(&path, man)
+}
+
+pub(crate) fn set_ids(root: &Path) -> Vec<String> {
+    let mut ids: Vec<String> = fs::read_dir(sets_dir(root))
+        .into_iter()
+        .flat_map(|entries| entries.flatten())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|e| e == "json"))
+        .filter_map(|path| Some(path.file_stem()?.to_string_lossy().to_string()))
+        .collect();
+    ids.sort();
+    ids
+}
+
+/// Todos os sets, do mais novo para o mais antigo. Um manifesto ilegível não
+/// esconde os outros: vai para a lista de erros.
+pub(crate) fn load_manifests(root: &Path) -> (Vec<SetManifest>, Vec<BlkError>) {
+    let mut sets = Vec::new();
+    let mut broken = Vec::new();
+    for id in set_ids(root) {
+        match load_manifest(root, &id) {
+            Ok(Some(man)) => sets.push(man),
+            Ok(None) => {}
+            Err(e) => broken.push(e),
+        }
+    }
+    sets.sort_by_key(|m| std::cmp::Reverse(m.created_at));
+    (sets, broken)
+}
+
+/// Cadeia de manifestos da raiz até `id`. Um pai ausente encerra a cadeia;
+/// um pai ilegível é erro (restaurar meia cadeia seria pior).
+pub(crate) fn resolve_chain(root: &Path, id: &str) -> BlkResult<Vec<SetManifest>> {
+    let mut chain: Vec<SetManifest> = Vec::new();
+    let mut cursor = Some(id.to_string());
+
+    while let Some(current) = cursor {
+        if chain.iter().any(|m| m.id == current) {
+            break;
+        }
+        match load_manifest(root, &current)? {
+            Some(man) => {
+                cursor = man.parent_id.clone();
+                chain.push(man);
+            }
+            None => break,
+        }
+    }
+
+    chain.reverse();
+    Ok(chain)
+}
+
+// ------------------------------- baseline ------------------------------------
+
+// Sem baseline ainda = mapa vazio; baseline corrompido é erro, não "tudo novo"
+pub(crate) fn load_baseline(root: &Path) -> BlkResult<HashMap<String, FileEntry>> {
+    Ok(read_json(&baseline_path(root))?.unwrap_or_default())
+}
+
+// Compacto: pode ter centenas de milhares de entradas
+pub(crate) fn save_baseline(root: &Path, map: &HashMap<String, FileEntry>) -> BlkResult<()> {
+    let path = baseline_path(root);
+    let json = serde_json::to_string(map).at("encode", &path)?;
+    write_atomic(&path, json.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::set_manifest;
+
+    fn repo() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(sets_dir(dir.path())).unwrap();
+        dir
+    }
+
+    #[test]
+    fn json_write_replaces_the_file_without_leaving_a_temp() {
+        let dir = repo();
+        let path = dir.path().join("value.json");
+        write_json(&path, &vec![1, 2]).unwrap();
+        write_json(&path, &vec![3]).unwrap();
+
+        assert_eq!(read_json::<Vec<u32>>(&path).unwrap(), Some(vec![3]));
+        assert!(!dir.path().join("value.json.tmp").exists());
+    }
+
+    #[test]
+    fn failed_json_write_keeps_the_old_file_and_cleans_up() {
+        let dir = repo();
+        // Um diretório no lugar do destino faz o rename falhar
+        let path = dir.path().join("busy.json");
+        fs::create_dir(&path).unwrap();
+
+        assert!(write_json(&path, &vec![1]).is_err());
+        assert!(path.is_dir());
+        assert!(!dir.path().join("busy.json.tmp").exists());
+    }
+
+    #[test]
+    fn missing_json_is_none_but_corrupted_json_is_an_error() {
+        let dir = repo();
+        let path = dir.path().join("value.json");
+        assert_eq!(read_json::<Vec<u32>>(&path).unwrap(), None);
+
+        fs::write(&path, "[1, 2").unwrap();
+        assert!(matches!(read_json::<Vec<u32>>(&path), Err(BlkError::Json { .. })));
+    }
+
+    #[test]
+    fn manifest_ids_must_be_plain_file_names() {
+        let dir = repo();
+        let cases = [("../up", None), ("a/b", None), ("..", None), ("ok", Some("../up")), ("ok", Some("ok"))];
+        // Nomes que o Windows não aceita
+        let odd = [("c:x", None), ("con", None), ("Nul.txt", None), ("com1", None), ("ok", Some("lpt9")), ("a b", None), ("é", None)];
+        for (id, parent) in cases.into_iter().chain(odd) {
+            let man = set_manifest(id, parent);
+            assert!(
+                matches!(save_manifest(dir.path(), &man), Err(BlkError::Integrity(_))),
+                "{id} / {parent:?}"
+            );
+        }
+        assert!(set_ids(dir.path()).is_empty());
+        for id in ["v1.2_final-b", "console", "com10", "COM0"] {
+            save_manifest(dir.path(), &set_manifest(id, None)).unwrap();
+        }
+
+        // Manifesto renomeado à mão: o id de dentro não bate com o arquivo
+        let json = serde_json::to_string(&set_manifest("other", None)).unwrap();
+        fs::write(manifest_path(dir.path(), "renamed"), json).unwrap();
+        assert!(matches!(load_manifest(dir.path(), "renamed"), Err(BlkError::Integrity(_))));
+    }
+
+    #[test]
+    fn chain_stops_at_a_missing_parent_and_at_a_cycle() {
+        let dir = repo();
+        save_manifest(dir.path(), &set_manifest("b", Some("gone"))).unwrap();
+        save_manifest(dir.path(), &set_manifest("c", Some("b"))).unwrap();
+        save_manifest(dir.path(), &set_manifest("x", Some("y"))).unwrap();
+        save_manifest(dir.path(), &set_manifest("y", Some("x"))).unwrap();
+
+        let ids = |id| -> Vec<String> {
+            resolve_chain(dir.path(), id).unwrap().into_iter().map(|m| m.id).collect()
+        };
+        assert_eq!(ids("c"), ["b", "c"]);
+        assert_eq!(ids("x"), ["y", "x"]);
+    }
+}
@@ -0,0 +1,215 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::engine::now_unix;
+use crate::error::{BlkError, BlkResult, Context, Report};
+use crate::lock::RepoLock;
+use crate::meta::{blk_dir, load_config, load_format, save_config, save_format};
+use crate::models::RepoFormat;
+use crate::paths::ROOT_VAR;
+
+// -----------------------------------------------------------------------------
+// Formato do repositório
+// -----------------------------------------------------------------------------
+//
+// `.blk/format.json` diz qual layout o repositório usa. Um repositório mais
+// velho é atualizado passo a passo ao abrir; um mais novo é recusado, porque
+// ler um layout desconhecido com as regras antigas estragaria os dados.
+
+/// Versão do layout que esta build lê e grava.
+pub const FORMAT_VERSION: u32 = 2;
+
+// Repositórios de antes do format.json
+const LEGACY_FORMAT: u32 = 1;
+
+/// Um passo de atualização: leva o repositório de `to - 1` para `to`.
+/// Precisa poder rodar de novo se foi interrompido no meio.
+struct Migration {
+    to: u32,
+    label: &'static str,
+    run: fn(&Path, &mut Report) -> BlkResult<()>,
+}
+
+const MIGRATIONS: &[Migration] = &[Migration {
+    to: 2,
+    label: "portable scope paths",
+    run: migrate_v2,
+}];
+
+/// Versão atual do repositório em `root`; erro se ela é mais nova que a da build.
+pub(crate) fn format_version(root: &Path) -> BlkResult<u32> {
+    let version = load_format(root)?.map_or(LEGACY_FORMAT, |f| f.version);
+    if version > FORMAT_VERSION {
+        return Err(BlkError::Integrity(format!(
+            "{} uses repository format v{version}, but this blk only understands up to v{FORMAT_VERSION}. Update blk to open it.",
+            root.to_string_lossy()
+        )));
+    }
+    Ok(version)
+}
+
+/// Marca um repositório recém-criado com a versão atual.
+pub(crate) fn stamp_format(root: &Path) -> BlkResult<()> {
+    save_format(
+        root,
+        &RepoFormat {
+            version: FORMAT_VERSION,
+            migrated_at: 0,
+        },
+    )
+}
+
+/// Aplica as migrações pendentes. `None` se o repositório já estava em dia.
+/// A versão é gravada depois de cada passo: se um falhar, o próximo
+/// `upgrade` recomeça dele.
+pub(crate) fn upgrade(root: &Path) -> BlkResult<Option<Report>> {
+    let from = format_version(root)?;
+    if from == FORMAT_VERSION {
+        return Ok(None);
+    }
+
+    let _lock = RepoLock::acquire(root, "upgrade")?;
+    upgrade_locked(root)
+}
+
+/// Como `upgrade`, para quem já segura o lock de `root`.
+pub(crate) fn upgrade_locked(root: &Path) -> BlkResult<Option<Report>> {
+    // Outro processo pode ter migrado enquanto esperávamos
+    let from = format_version(root)?;
+    if from == FORMAT_VERSION {
+        return Ok(None);
+    }
+
+    let mut report = Report::new(format!(
+        "Upgraded repository format v{from} → v{FORMAT_VERSION}"
+    ));
+    for step in MIGRATIONS.iter().filter(|m| m.to > from) {
+        (step.run)(root, &mut report).map_err(|e| {
+            BlkError::Aborted(format!(
+                "Upgrade to format v{} ({}) failed: {e}",
+                step.to, step.label
+            ))
+        })?;
+        save_format(
+            root,
+            &RepoFormat {
+                version: step.to,
+                migrated_at: now_unix(),
+            },
+        )?;
+    }
+    Ok(Some(report))
+}
+
+// --------------------------------- v2 ----------------------------------------
+
+// Pastas de trabalho das versões que copiavam tudo antes de zipar/extrair
+const LEGACY_WORK_DIRS: &[&str] = &["staging", "staging_vanilla", "tmp_extract"];
+
+/// Caminho absoluto dentro de `root` vira `${ROOT}/...`.
+fn portable(path: &Path, root: &Path) -> Option<PathBuf> {
+    let canonical = root.canonicalize().ok();
+    let rel = path
+        .strip_prefix(root)
+        .ok()
+        .or_else(|| path.strip_prefix(canonical.as_deref()?).ok())?;
+    if rel.as_os_str().is_empty() {
+        return Some(PathBuf::from(ROOT_VAR));
+    }
+    let rel = rel.to_string_lossy().replace('\\', "/");
+    Some(PathBuf::from(format!("{ROOT_VAR}/{rel}")))
+}
+
+/// v1 → v2: o path_map gravava o caminho absoluto da pasta do jogo, e o
+/// repositório parava de funcionar ao ser movido. Sobras de staging também
+/// ficavam em `.blk` quando uma operação antiga era interrompida.
+fn migrate_v2(root: &Path, report: &mut Report) -> BlkResult<()> {
+    let mut config = load_config(root)?;
+    let mut changed = false;
+    for path in config.path_map.values_mut() {
+        if let Some(rewritten) = portable(path, root) {
+            *path = rewritten;
+            changed = true;
+        }
+    }
+    if changed {
+        save_config(root, &config)?;
+    }
+
+    for name in LEGACY_WORK_DIRS {
+        let dir = blk_dir(root).join(name);
+        if dir.exists() {
+            report.check(fs::remove_dir_all(&dir).at("remove", &dir));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::models::BlkConfig;
+
+    // Repositório v1: sem format.json, caminhos absolutos e sobras de staging
+    fn make_legacy(root: &Path, path_map: &[(&str, PathBuf)]) {
+        fs::create_dir_all(blk_dir(root).join("staging/deep")).unwrap();
+        let config = BlkConfig {
+            path_map: path_map.iter().map(|(k, p)| (k.to_string(), p.clone())).collect(),
+            ..BlkConfig::default()
+        };
+        save_config(root, &config).unwrap();
+    }
+
+    #[test]
+    fn v1_paths_inside_the_repo_become_portable() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        make_legacy(
+            root,
+            &[
+                ("Root", root.to_path_buf()),
+                ("Mods", root.join("mods")),
+                ("Saves", outside.path().to_path_buf()),
+            ],
+        );
+
+        let report = upgrade(root).unwrap().expect("a v1 repository needs the upgrade");
+
+        assert!(report.failures.is_empty(), "{:?}", report.failures);
+        let config = load_config(root).unwrap();
+        assert_eq!(config.path_map["Root"], PathBuf::from("${ROOT}"));
+        assert_eq!(config.path_map["Mods"], PathBuf::from("${ROOT}/mods"));
+        assert_eq!(config.path_map["Saves"], outside.path());
+        assert!(!blk_dir(root).join("staging").exists());
+        assert_eq!(format_version(root).unwrap(), FORMAT_VERSION);
+        assert!(upgrade(root).unwrap().is_none());
+    }
+
+    #[test]
+    fn newer_format_is_refused_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        make_legacy(root, &[("Root", root.to_path_buf())]);
+        let newer = RepoFormat {
+            version: FORMAT_VERSION + 1,
+            migrated_at: 0,
+        };
+        save_format(root, &newer).unwrap();
+
+        assert!(matches!(upgrade(root), Err(BlkError::Integrity(_))));
+        assert_eq!(load_config(root).unwrap().path_map["Root"], root);
+        assert!(blk_dir(root).join("staging").exists());
+    }
+
+    #[test]
+    fn new_repository_is_stamped_current() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(blk_dir(dir.path())).unwrap();
+        stamp_format(dir.path()).unwrap();
+
+        assert_eq!(format_version(dir.path()).unwrap(), FORMAT_VERSION);
+        assert!(upgrade(dir.path()).unwrap().is_none());
+    }
+}
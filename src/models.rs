@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct SetManifest {
     pub id: String,
     pub name: String,
@@ -21,11 +21,27 @@ pub struct SetManifest {
     pub deleted_paths: Vec<String>, 
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    #[default]
+    File,
+    Dir,
+    Symlink,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct FileEntry {
     pub hash: String,
     pub size: u64,
     pub modified: u64,
+
+    #[serde(default)]
+    pub kind: EntryKind,
+
+    // Bits de permissão (0 = desconhecido, baselines antigos)
+    #[serde(default)]
+    pub mode: u32,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -36,7 +52,173 @@ pub struct DiffSummary {
     pub is_dirty: bool,
 }
 
+/// Situação no disco de uma entrada do path_map.
+#[derive(Debug, Clone)]
+pub struct ScopeStatus {
+    pub key: String,
+    // Como está no config.json (pode ter ${VARIÁVEIS})
+    pub raw: PathBuf,
+    // Expandido; Err com a mensagem se uma variável não resolve
+    pub resolved: Result<PathBuf, String>,
+    pub exists: bool,
+    pub is_dir: bool,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Um caminho de um set como aparece no índice (diretório central) do zip.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentEntry {
+    // "Scope/rel/path", como no zip
+    pub path: String,
+    pub kind: EntryKind,
+    pub size: u64,
+    // CRC32 gravado no zip; é o único hash que se lê sem extrair
+    pub crc32: u32,
+    // Set cuja camada fornece (ou apaga) o caminho
+    pub source: String,
+    // Removido por um `deleted_paths` de `source`
+    pub deleted: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Codec {
+    Store,
+    Deflate,
+    #[default]
+    Zstd,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionProfile {
+    // Nível baixo para iterar rápido
+    Fast,
+    #[default]
+    Balanced,
+    // Nível alto para sets de arquivo
+    Max,
+}
+
+impl CompressionProfile {
+    pub fn next(self) -> Self {
+        match self {
+            CompressionProfile::Fast => CompressionProfile::Balanced,
+            CompressionProfile::Balanced => CompressionProfile::Max,
+            CompressionProfile::Max => CompressionProfile::Fast,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CompressionProfile::Fast => "fast",
+            CompressionProfile::Balanced => "balanced",
+            CompressionProfile::Max => "max",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CompressionConfig {
+    #[serde(default)]
+    pub codec: Codec,
+
+    #[serde(default)]
+    pub profile: CompressionProfile,
+
+    // Nível explícito; se ausente, vem do profile
+    #[serde(default)]
+    pub level: Option<i64>,
+
+    // Extensão (sem ponto, minúscula) -> codec
+    #[serde(default = "default_codec_overrides")]
+    pub overrides: HashMap<String, Codec>,
+}
+
+// Formatos já comprimidos: recomprimir só gasta CPU
+fn default_codec_overrides() -> HashMap<String, Codec> {
+    [
+        "kn5", "dds", "ogg", "mp3", "mp4", "bik", "png", "jpg", "jpeg", "zip", "7z", "rar",
+        "acd",
+    ]
+    .iter()
+    .map(|ext| (ext.to_string(), Codec::Store))
+    .collect()
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        CompressionConfig {
+            codec: Codec::default(),
+            profile: CompressionProfile::default(),
+            level: None,
+            overrides: default_codec_overrides(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct BlkConfig {
     pub path_map: HashMap<String, PathBuf>,
-}
\ No newline at end of file
+
+    #[serde(default)]
+    pub compression: CompressionConfig,
+
+    // Pasta dos zips das camadas; None = .blk/snapshots. Aceita ${VARIÁVEIS}
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage: Option<PathBuf>,
+
+    // Último repositório usado em push/pull
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote: Option<PathBuf>,
+}
+
+/// Hash em cache de uma camada (`.blk/layers.json`), válido enquanto
+/// tamanho e mtime do zip não mudarem.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LayerInfo {
+    pub size: u64,
+    pub modified: u64,
+    pub sha256: String,
+}
+
+/// Conteúdo de `.blk/format.json`: versão do layout do repositório.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RepoFormat {
+    pub version: u32,
+
+    // Quando a última migração rodou (0 = criado já nesta versão)
+    #[serde(default)]
+    pub migrated_at: u64,
+}
+
+/// Conteúdo de `.blk/lock` enquanto uma operação altera o repositório.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LockInfo {
+    pub pid: u32,
+    pub operation: String,
+    pub started_at: u64,
+
+    // Máquina dona do PID (o repo pode estar num compartilhamento de rede)
+    #[serde(default)]
+    pub host: String,
+}
+
+/// Conteúdo de `bundle.json` dentro de um pacote exportado (.blkb).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BundleManifest {
+    pub format_version: u32,
+    pub created_at: u64,
+    pub target_id: String,
+    pub flattened: bool,
+
+    // Nomes de scope usados pelas camadas (chaves do path_map de origem)
+    pub scopes: Vec<String>,
+
+    // Cadeia ordenada da raiz até o alvo
+    pub sets: Vec<SetManifest>,
+
+    // Membro do pacote -> sha256
+    pub checksums: HashMap<String, String>,
+}
@@ -0,0 +1,219 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{BlkError, BlkResult};
+use crate::models::BlkConfig;
+
+// -----------------------------------------------------------------------------
+// Variáveis de caminho
+// -----------------------------------------------------------------------------
+//
+// O path_map guarda os caminhos como o usuário escreveu ("${ROOT}/content",
+// "${DOCUMENTS}/Assetto Corsa"). A expansão acontece só na hora de usar, então
+// o mesmo .blk continua valendo depois de mover o jogo ou trocar de usuário.
+
+pub const ROOT_VAR: &str = "${ROOT}";
+
+// Variáveis embutidas, oferecidas no autocompletar
+const BUILTIN_VARS: &[&str] = &["ROOT", "HOME", "DOCUMENTS", "APPDATA"];
+
+fn home_dir() -> Option<PathBuf> {
+    let var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    env::var_os(var).filter(|v| !v.is_empty()).map(PathBuf::from)
+}
+
+fn documents_dir() -> Option<PathBuf> {
+    if !cfg!(windows) {
+        if let Some(dir) = env::var_os("XDG_DOCUMENTS_DIR").filter(|v| !v.is_empty()) {
+            return Some(PathBuf::from(dir));
+        }
+    }
+    home_dir().map(|h| h.join("Documents"))
+}
+
+fn appdata_dir() -> Option<PathBuf> {
+    let var = if cfg!(windows) { "APPDATA" } else { "XDG_CONFIG_HOME" };
+    if let Some(dir) = env::var_os(var).filter(|v| !v.is_empty()) {
+        return Some(PathBuf::from(dir));
+    }
+    if cfg!(windows) {
+        home_dir().map(|h| h.join("AppData").join("Roaming"))
+    } else {
+        home_dir().map(|h| h.join(".config"))
+    }
+}
+
+/// Valor de uma variável: as embutidas primeiro, depois o ambiente.
+fn lookup_var(name: &str, app_root: &Path) -> Option<String> {
+    let builtin = match name {
+        "ROOT" => Some(app_root.to_path_buf()),
+        "HOME" => home_dir(),
+        "DOCUMENTS" => documents_dir(),
+        "APPDATA" => appdata_dir(),
+        _ => None,
+    };
+    match builtin {
+        Some(p) => Some(p.to_string_lossy().to_string()),
+        None => env::var(name).ok().filter(|v| !v.is_empty()),
+    }
+}
+
+/// Expande `${NAME}` num caminho do path_map. Caminhos sem variáveis voltam iguais.
+pub fn expand_path(raw: &Path, app_root: &Path) -> BlkResult<PathBuf> {
+    let text = raw.to_string_lossy();
+    if !text.contains("${") {
+        return Ok(raw.to_path_buf());
+    }
+
+    let mut out = String::new();
+    let mut rest: &str = &text;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| BlkError::Config(format!("Unclosed variable in '{text}'")))?;
+        let name = &after[..end];
+        let value = lookup_var(name, app_root)
+            .ok_or_else(|| BlkError::Config(format!("Unknown variable ${{{name}}} in '{text}'")))?;
+        out.push_str(&value);
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+
+    Ok(PathBuf::from(out))
+}
+
+/// Cópia da config com todos os caminhos do path_map já expandidos.
+pub fn resolve_path_map(config: &BlkConfig, app_root: &Path) -> BlkResult<BlkConfig> {
+    let mut resolved = config.clone();
+    for (scope, path) in resolved.path_map.iter_mut() {
+        *path = expand_path(path, app_root)
+            .map_err(|e| BlkError::Config(format!("Scope '{scope}': {e}")))?;
+    }
+    Ok(resolved)
+}
+
+/// Candidatos para completar o último pedaço de `partial`: pastas
+/// ("${ROOT}/con" → "${ROOT}/content/") ou variáveis ("${DO" → "${DOCUMENTS}/").
+/// O começo do texto fica como o usuário escreveu.
+pub fn complete_path(partial: &str, app_root: &Path) -> Vec<String> {
+    let split = partial.rfind(['/', '\\']).map_or(0, |i| i + 1);
+    let (dir_part, prefix) = partial.split_at(split);
+    let sep = if dir_part.ends_with('\\') { '\\' } else { '/' };
+
+    if let Some(var) = prefix.strip_prefix("${").filter(|v| !v.contains('}')) {
+        let var = var.to_uppercase();
+        return BUILTIN_VARS
+            .iter()
+            .filter(|name| name.starts_with(&var))
+            .map(|name| format!("{dir_part}${{{name}}}{sep}"))
+            .collect();
+    }
+
+    let dir = match expand_path(Path::new(dir_part), app_root) {
+        Ok(dir) if dir_part.is_empty() || dir.is_relative() => app_root.join(dir),
+        Ok(dir) => dir,
+        Err(_) => return Vec::new(),
+    };
+    let lower = prefix.to_lowercase();
+    let mut found: Vec<String> = fs::read_dir(&dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+        .filter(|name| name.to_lowercase().starts_with(&lower))
+        // Ocultas só quando pedidas
+        .filter(|name| !name.starts_with('.') || prefix.starts_with('.'))
+        .map(|name| format!("{dir_part}{name}{sep}"))
+        .collect();
+    found.sort();
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::engine_auto_init;
+    use crate::meta::load_config;
+    use crate::test_support::silent;
+
+    #[test]
+    fn variables_expand_from_builtins_then_the_environment() {
+        let root = Path::new("/games/ac");
+        let expand = |raw: &str| expand_path(Path::new(raw), root);
+
+        assert_eq!(expand("${ROOT}/content").unwrap(), Path::new("/games/ac/content"));
+        assert_eq!(expand("/plain/path").unwrap(), Path::new("/plain/path"));
+        // Cargo define essa variável para o teste
+        let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+        assert_eq!(expand("${CARGO_MANIFEST_DIR}/src").unwrap(), Path::new(&manifest_dir).join("src"));
+
+        assert!(matches!(expand("${NO_SUCH_BLK_VAR}/x"), Err(BlkError::Config(_))));
+        assert!(matches!(expand("${ROOT/x"), Err(BlkError::Config(_))));
+    }
+
+    #[test]
+    fn moved_repository_resolves_root_to_its_new_place() {
+        let outer = tempfile::tempdir().unwrap();
+        let before = outer.path().join("before");
+        fs::create_dir(&before).unwrap();
+        engine_auto_init(&before, silent()).unwrap();
+        let after = outer.path().join("after");
+        fs::rename(&before, &after).unwrap();
+
+        let config = load_config(&after).unwrap();
+        assert_eq!(config.path_map["Root"], Path::new(ROOT_VAR));
+        assert_eq!(resolve_path_map(&config, &after).unwrap().path_map["Root"], after);
+
+        let mut broken = config.clone();
+        broken.path_map.insert("Mods".into(), PathBuf::from("${NO_SUCH_BLK_VAR}/mods"));
+        match resolve_path_map(&broken, &after) {
+            Err(BlkError::Config(msg)) => assert!(msg.contains("'Mods'"), "{msg}"),
+            other => panic!("expected Config, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn completion_offers_folders_and_variables_keeping_what_was_typed() {
+        let root = tempfile::tempdir().unwrap();
+        for dir in ["content/cars", "Config", ".hidden", "cfg_file_dir"] {
+            fs::create_dir_all(root.path().join(dir)).unwrap();
+        }
+        fs::write(root.path().join("content/cars.txt"), "not a folder").unwrap();
+        let complete = |partial: &str| complete_path(partial, root.path());
+
+        assert_eq!(complete("c"), ["Config/", "cfg_file_dir/", "content/"]);
+        assert_eq!(complete("${ROOT}/CON"), ["${ROOT}/Config/", "${ROOT}/content/"]);
+        assert_eq!(complete("content/ca"), ["content/cars/"]);
+        if cfg!(windows) {
+            assert_eq!(complete("content\\ca"), ["content\\cars\\"]);
+        }
+        assert!(complete("h").is_empty());
+        assert_eq!(complete("."), [".hidden/"]);
+        assert_eq!(complete("${do"), ["${DOCUMENTS}/"]);
+        assert!(complete("${NO_SUCH_BLK_VAR}/x").is_empty());
+    }
+
+    #[test]
+    fn probe_tells_a_folder_from_a_file_and_a_missing_path() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("mods/sub")).unwrap();
+        fs::write(root.path().join("mods/a.pak"), "1234").unwrap();
+        fs::write(root.path().join("mods/sub/b.pak"), "56").unwrap();
+
+        let dir = crate::engine::engine_scope_status(root.path(), "Mods", Path::new("${ROOT}/mods"));
+        assert!(dir.exists && dir.is_dir);
+        assert_eq!((dir.files, dir.bytes), (2, 6));
+        assert_eq!(dir.resolved.unwrap(), root.path().join("mods"));
+
+        let file = crate::engine::engine_scope_status(root.path(), "", Path::new("${ROOT}/mods/a.pak"));
+        assert!(file.exists && !file.is_dir);
+        let missing = crate::engine::engine_scope_status(root.path(), "", Path::new("${ROOT}/nope"));
+        assert!(!missing.exists);
+        let unknown = crate::engine::engine_scope_status(root.path(), "", Path::new("${NO_SUCH_BLK_VAR}"));
+        assert!(unknown.resolved.is_err());
+    }
+}
@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::bundle::{default_export_path, engine_export_bundle, engine_import_bundle};
+use crate::engine::{
+    engine_auto_init, engine_check_changes, engine_delete_cascade, engine_remove_global_path,
+    engine_restore_chain, engine_save_new_delta, engine_scope_status, engine_set_contents,
+    engine_update_compression,
+    engine_update_global_path, engine_update_manifest, engine_update_remote, engine_verify,
+    get_snapshot_size,
+};
+use crate::error::{BlkError, BlkResult, Report};
+use crate::events::{CancelToken, EventSender};
+use crate::meta::{blk_dir, load_config, load_manifest, load_manifests};
+use crate::migrate::upgrade;
+use crate::models::{
+    BlkConfig, CompressionProfile, ContentEntry, DiffSummary, ScopeStatus, SetManifest,
+};
+use crate::sync::{engine_pull, engine_push};
+
+// -----------------------------------------------------------------------------
+// Repository
+// -----------------------------------------------------------------------------
+//
+// Porta de entrada da biblioteca. As operações longas rodam na thread de quem
+// chama e só falam pelo `EventSender`; a TUI as dispara em background com um
+// clone do handle. Config e manifestos ficam em memória e são relidos depois
+// de cada operação que os altera.
+
+/// Um diretório com `.blk`, com a config e os sets já carregados.
+#[derive(Debug, Clone)]
+pub struct Repository {
+    root: PathBuf,
+    config: BlkConfig,
+    sets: Vec<SetManifest>,
+    // Mensagens dos manifestos que não puderam ser lidos
+    unreadable: Vec<String>,
+    // Resultado da migração feita no `open`, se houve
+    upgraded: Option<String>,
+}
+
+impl Repository {
+    /// Abre um repositório existente. Sem `config.json` vale a config padrão;
+    /// uma config corrompida é erro. Um formato antigo é migrado antes de
+    /// ler qualquer coisa; um formato mais novo que o desta build é recusado.
+    pub fn open(root: impl Into<PathBuf>) -> BlkResult<Self> {
+        let root = root.into();
+        if !blk_dir(&root).is_dir() {
+            return Err(BlkError::NotFound(format!(
+                "No BLK repository at {}",
+                root.display()
+            )));
+        }
+
+        let upgraded = upgrade(&root)?.map(|report| report.to_string());
+        let config = load_config(&root)?;
+        let mut repo = Repository::with_config(root, config);
+        repo.upgraded = upgraded;
+        repo.reload_sets();
+        Ok(repo)
+    }
+
+    /// Handle sem ler o disco: antes do `init`, ou com uma config montada em memória.
+    pub fn with_config(root: impl Into<PathBuf>, config: BlkConfig) -> Self {
+        Repository {
+            root: root.into(),
+            config,
+            sets: Vec::new(),
+            unreadable: Vec::new(),
+            upgraded: None,
+        }
+    }
+
+    /// Relê os manifestos (outro processo ou outro handle pode tê-los mudado).
+    pub fn reload_sets(&mut self) {
+        let (sets, broken) = load_manifests(&self.root);
+        self.sets = sets;
+        self.unreadable = broken.iter().map(|e| e.to_string()).collect();
+    }
+
+    /// Cria o `.blk` em `root` com o snapshot vanilla do que já existe lá.
+    pub fn init(root: &Path, tx: EventSender) -> BlkResult<Report> {
+        engine_auto_init(root, tx)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn config(&self) -> &BlkConfig {
+        &self.config
+    }
+
+    // ------------------------------ sets -----------------------------------
+
+    /// Todos os sets legíveis, do mais novo para o mais antigo.
+    pub fn sets(&self) -> &[SetManifest] {
+        &self.sets
+    }
+
+    /// Um erro por manifesto que existe mas não pôde ser lido ou validado.
+    pub fn unreadable_sets(&self) -> &[String] {
+        &self.unreadable
+    }
+
+    /// Resumo da migração de formato feita ao abrir, se foi preciso migrar.
+    pub fn upgrade_note(&self) -> Option<&str> {
+        self.upgraded.as_deref()
+    }
+
+    pub fn set(&self, id: &str) -> Option<&SetManifest> {
+        self.sets.iter().find(|m| m.id == id)
+    }
+
+    /// Linhagem de `id`, da raiz até ele. Para no primeiro pai ausente.
+    pub fn chain(&self, id: &str) -> Vec<SetManifest> {
+        let mut chain: Vec<SetManifest> = Vec::new();
+        let mut cursor = self.set(id);
+        while let Some(man) = cursor {
+            if chain.iter().any(|m| m.id == man.id) {
+                break;
+            }
+            chain.push(man.clone());
+            cursor = man.parent_id.as_deref().and_then(|p| self.set(p));
+        }
+        chain.reverse();
+        chain
+    }
+
+    pub fn snapshot_size(&self, id: &str) -> u64 {
+        get_snapshot_size(&self.root, &self.config, id)
+    }
+
+    /// O que `id` contém, lido do índice das camadas. `full`: o estado que a
+    /// cadeia inteira reconstrói; senão, só a camada do próprio set.
+    pub fn contents(&self, id: &str, full: bool) -> BlkResult<Vec<ContentEntry>> {
+        self.require_set(id)?;
+        let ids: Vec<String> = if full {
+            self.chain(id).into_iter().map(|m| m.id).collect()
+        } else {
+            vec![id.to_string()]
+        };
+        engine_set_contents(&self.root, &self.config, &ids)
+    }
+
+    pub fn export_path(&self, id: &str, flatten: bool) -> PathBuf {
+        default_export_path(&self.root, id, flatten)
+    }
+
+    /// Chaves do path_map em ordem alfabética.
+    pub fn scope_keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.config.path_map.keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+
+    /// Existência e tamanho do caminho de `key`. Percorre o scope inteiro.
+    pub fn scope_status(&self, key: &str) -> Option<ScopeStatus> {
+        let raw = self.config.path_map.get(key)?;
+        Some(engine_scope_status(&self.root, key, raw))
+    }
+
+    /// Como `scope_status`, para um caminho que ainda não está no path_map.
+    pub fn probe_path(&self, raw: &str) -> ScopeStatus {
+        engine_scope_status(&self.root, "", Path::new(raw))
+    }
+
+    /// Sets que restauram `key` (removê-la deixa esses scopes de fora).
+    pub fn sets_using_scope(&self, key: &str) -> Vec<&SetManifest> {
+        self.sets
+            .iter()
+            .filter(|m| m.scopes.iter().any(|s| s == key))
+            .collect()
+    }
+
+    // Scopes e exclusões de um set; sem set, todo o path_map. Lidos do disco:
+    // a config de um set é gravada por outro handle (numa thread) e o cache
+    // deste pode estar para trás.
+    fn scopes_of(&self, id: Option<&str>) -> BlkResult<(Vec<String>, Vec<String>)> {
+        let man = match id {
+            Some(id) => load_manifest(&self.root, id)?,
+            None => None,
+        };
+        Ok(match man {
+            Some(man) => (man.scopes, man.exclusions),
+            None => (self.config.path_map.keys().cloned().collect(), vec![]),
+        })
+    }
+
+    fn require_set(&self, id: &str) -> BlkResult<&SetManifest> {
+        self.set(id)
+            .ok_or_else(|| BlkError::NotFound(format!("Set '{id}' not found")))
+    }
+
+    // --------------------------- operações ---------------------------------
+
+    /// Diferenças entre o disco e o baseline, nos scopes do set `id`.
+    pub fn diff(&self, id: Option<&str>) -> BlkResult<DiffSummary> {
+        let (scopes, exclusions) = self.scopes_of(id)?;
+        engine_check_changes(&self.root, self.config.clone(), scopes, exclusions)
+    }
+
+    /// Grava uma camada nova sobre `parent`, com os scopes dele.
+    pub fn save(
+        &mut self,
+        name: &str,
+        parent: Option<&str>,
+        cancel: CancelToken,
+        tx: EventSender,
+    ) -> BlkResult<Report> {
+        let (scopes, exclusions) = self.scopes_of(parent)?;
+        let outcome = engine_save_new_delta(
+            &self.root,
+            self.config.clone(),
+            name.to_string(),
+            parent.map(str::to_string),
+            scopes,
+            exclusions,
+            cancel,
+            tx,
+        );
+        self.reload_sets();
+        outcome
+    }
+
+    /// Wipe + rebuild dos scopes de `id` a partir da sua cadeia inteira.
+    pub fn restore(&self, id: &str, cancel: CancelToken, tx: EventSender) -> BlkResult<Report> {
+        // Do disco, como em `scopes_of`: o wipe não pode usar scopes velhos
+        let man = load_manifest(&self.root, id)?
+            .ok_or_else(|| BlkError::NotFound(format!("Set '{id}' not found")))?;
+        let ids = self.chain(id).into_iter().map(|m| m.id).collect();
+        engine_restore_chain(
+            &self.root,
+            self.config.clone(),
+            ids,
+            man.scopes,
+            man.exclusions,
+            cancel,
+            tx,
+        )
+    }
+
+    /// Apaga `id` e todos os descendentes.
+    pub fn delete(&mut self, id: &str, tx: EventSender) -> BlkResult<Report> {
+        self.require_set(id)?;
+        let outcome =
+            engine_delete_cascade(&self.root, self.config.clone(), id.to_string(), &self.sets, tx);
+        self.reload_sets();
+        outcome
+    }
+
+    /// Confere camadas e linhagem dos sets carregados; manifestos ilegíveis
+    /// também contam como falha.
+    pub fn verify(&self, cancel: CancelToken, tx: EventSender) -> BlkResult<Report> {
+        let mut report = engine_verify(&self.root, self.config.clone(), &self.sets, cancel, tx)?;
+        for msg in &self.unreadable {
+            report.fail(BlkError::Integrity(msg.clone()));
+        }
+        if !report.failures.is_empty() {
+            report.summary = "Verification found problems".into();
+        }
+        Ok(report)
+    }
+
+    pub fn export(
+        &self,
+        id: &str,
+        dest: PathBuf,
+        flatten: bool,
+        cancel: CancelToken,
+        tx: EventSender,
+    ) -> BlkResult<Report> {
+        engine_export_bundle(&self.root, self.config.clone(), id.to_string(), dest, flatten, cancel, tx)
+    }
+
+    /// `scope_map` leva scopes do bundle para chaves do path_map local.
+    pub fn import(
+        &mut self,
+        bundle: PathBuf,
+        scope_map: HashMap<String, String>,
+        cancel: CancelToken,
+        tx: EventSender,
+    ) -> BlkResult<Report> {
+        let outcome =
+            engine_import_bundle(&self.root, self.config.clone(), bundle, scope_map, cancel, tx);
+        self.reload_sets();
+        outcome
+    }
+
+    pub fn push(&self, id: &str, remote: PathBuf, cancel: CancelToken, tx: EventSender) -> BlkResult<Report> {
+        engine_push(&self.root, self.config.clone(), id.to_string(), remote, cancel, tx)
+    }
+
+    pub fn pull(&mut self, remote: PathBuf, cancel: CancelToken, tx: EventSender) -> BlkResult<Report> {
+        let outcome = engine_pull(&self.root, self.config.clone(), remote, cancel, tx);
+        self.reload_sets();
+        outcome
+    }
+
+    // ----------------------------- config ----------------------------------
+
+    /// Troca scopes e exclusões de um set já gravado.
+    pub fn update_set(
+        &mut self,
+        id: &str,
+        scopes: Vec<String>,
+        exclusions: Vec<String>,
+        tx: EventSender,
+    ) -> BlkResult<Report> {
+        let outcome = engine_update_manifest(&self.root, id.to_string(), scopes, exclusions, tx);
+        self.reload_sets();
+        outcome
+    }
+
+    pub fn set_path(&mut self, key: &str, path: &str) -> BlkResult<()> {
+        engine_update_global_path(&self.root, key.to_string(), path.to_string())?;
+        self.config.path_map.insert(key.to_string(), PathBuf::from(path));
+        Ok(())
+    }
+
+    pub fn remove_path(&mut self, key: &str) -> BlkResult<()> {
+        engine_remove_global_path(&self.root, key)?;
+        self.config.path_map.remove(key);
+        Ok(())
+    }
+
+    pub fn set_compression(&mut self, profile: CompressionProfile) -> BlkResult<()> {
+        engine_update_compression(&self.root, profile)?;
+        self.config.compression.profile = profile;
+        Ok(())
+    }
+
+    pub fn set_remote(&mut self, remote: PathBuf) -> BlkResult<()> {
+        engine_update_remote(&self.root, remote.clone())?;
+        self.config.remote = Some(remote);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{init_repo, silent};
+
+    #[test]
+    fn operations_see_a_config_saved_through_another_handle() {
+        let dir = init_repo(&[("game.txt", "base")]);
+        let repo = Repository::open(dir.path()).unwrap();
+
+        // Como o popup de config: grava por um clone, numa thread
+        let mut other = repo.clone();
+        other
+            .update_set("vanilla", vec!["Root".into()], vec!["*.log".into()], silent())
+            .unwrap();
+
+        let (scopes, exclusions) = repo.scopes_of(Some("vanilla")).unwrap();
+        assert_eq!(scopes, ["Root"]);
+        assert_eq!(exclusions, ["*.log"]);
+    }
+}
@@ -0,0 +1,264 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::error::{BlkError, BlkResult};
+use crate::models::BlkConfig;
+use crate::paths::expand_path;
+
+// -----------------------------------------------------------------------------
+// Snapshot storage
+// -----------------------------------------------------------------------------
+//
+// Manifestos, config e baseline ficam sempre em .blk; só os zips das camadas
+// passam por aqui, e podem morar em outro disco (HDD grande, NAS montado...).
+
+pub trait ReadSeek: Read + Seek + Send {}
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+/// Destino de uma camada sendo gravada. Só aparece para os leitores depois
+/// do `commit`; se for descartado antes, não sobra nada pela metade.
+pub trait SnapshotWriter: Write + Seek + Send {
+    fn commit(self: Box<Self>) -> io::Result<()>;
+}
+
+pub trait SnapshotStore: Send + Sync {
+    /// Onde as camadas moram, para mensagens.
+    fn location(&self) -> String;
+    fn contains(&self, id: &str) -> bool;
+    fn size(&self, id: &str) -> Option<u64>;
+    /// mtime em segundos Unix; junto com o tamanho invalida hashes em cache.
+    fn modified(&self, id: &str) -> Option<u64>;
+    fn open(&self, id: &str) -> io::Result<Box<dyn ReadSeek>>;
+    fn create(&self, id: &str) -> io::Result<Box<dyn SnapshotWriter>>;
+    fn remove(&self, id: &str) -> io::Result<()>;
+
+    /// Guarda um zip pronto em disco local (consumindo o arquivo).
+    fn put_file(&self, id: &str, src: &Path) -> io::Result<()> {
+        let mut writer = self.create(id)?;
+        io::copy(&mut File::open(src)?, &mut writer)?;
+        writer.commit()?;
+        fs::remove_file(src)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Local directory
+// -----------------------------------------------------------------------------
+
+pub struct LocalDirStore {
+    dir: PathBuf,
+}
+
+impl LocalDirStore {
+    pub fn new(dir: PathBuf) -> Self {
+        LocalDirStore { dir }
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.zip"))
+    }
+}
+
+struct LocalWriter {
+    file: Option<File>,
+    partial: PathBuf,
+    dest: PathBuf,
+}
+
+impl Write for LocalWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.as_mut().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.as_mut().unwrap().flush()
+    }
+}
+
+impl Seek for LocalWriter {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.file.as_mut().unwrap().seek(pos)
+    }
+}
+
+impl SnapshotWriter for LocalWriter {
+    fn commit(mut self: Box<Self>) -> io::Result<()> {
+        let result = match self.file.take() {
+            // Fecha antes do rename (Windows não renomeia arquivo aberto)
+            Some(file) => file.sync_all().and_then(|_| {
+                drop(file);
+                fs::rename(&self.partial, &self.dest)
+            }),
+            None => Ok(()),
+        };
+        if result.is_err() {
+            let _ = fs::remove_file(&self.partial);
+        }
+        result
+    }
+}
+
+impl Drop for LocalWriter {
+    fn drop(&mut self) {
+        // Não chegou ao commit: descarta o .part
+        if self.file.take().is_some() {
+            let _ = fs::remove_file(&self.partial);
+        }
+    }
+}
+
+impl SnapshotStore for LocalDirStore {
+    fn location(&self) -> String {
+        self.dir.to_string_lossy().to_string()
+    }
+
+    fn contains(&self, id: &str) -> bool {
+        self.path(id).is_file()
+    }
+
+    fn size(&self, id: &str) -> Option<u64> {
+        fs::metadata(self.path(id)).ok().map(|m| m.len())
+    }
+
+    fn modified(&self, id: &str) -> Option<u64> {
+        let mtime = fs::metadata(self.path(id)).ok()?.modified().ok()?;
+        mtime.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+    }
+
+    fn open(&self, id: &str) -> io::Result<Box<dyn ReadSeek>> {
+        Ok(Box::new(File::open(self.path(id))?))
+    }
+
+    fn create(&self, id: &str) -> io::Result<Box<dyn SnapshotWriter>> {
+        fs::create_dir_all(&self.dir)?;
+        let dest = self.path(id);
+        let partial = self.dir.join(format!("{id}.zip.part"));
+        let file = File::create(&partial)?;
+        Ok(Box::new(LocalWriter {
+            file: Some(file),
+            partial,
+            dest,
+        }))
+    }
+
+    fn remove(&self, id: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(id)) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            other => other,
+        }
+    }
+
+    // Mesmo disco: rename; disco diferente cai na cópia
+    fn put_file(&self, id: &str, src: &Path) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        if fs::rename(src, self.path(id)).is_ok() {
+            return Ok(());
+        }
+        let mut writer = self.create(id)?;
+        io::copy(&mut File::open(src)?, &mut writer)?;
+        writer.commit()?;
+        fs::remove_file(src)
+    }
+}
+
+/// Abre o armazenamento configurado em `storage` (padrão: .blk/snapshots).
+pub fn open_store(app_root: &Path, config: &BlkConfig) -> BlkResult<Box<dyn SnapshotStore>> {
+    let dir = match &config.storage {
+        Some(raw) => {
+            let dir = expand_path(raw, app_root)
+                .map_err(|e| BlkError::Config(format!("Storage: {e}")))?;
+            if dir.is_relative() {
+                app_root.join(dir)
+            } else {
+                dir
+            }
+        }
+        None => app_root.join(".blk").join("snapshots"),
+    };
+    Ok(Box::new(LocalDirStore::new(dir)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::init_repo;
+
+    fn listing(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn layer_shows_up_only_after_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalDirStore::new(dir.path().join("layers"));
+
+        let mut dropped = store.create("a").unwrap();
+        dropped.write_all(b"half").unwrap();
+        drop(dropped);
+        assert!(!store.contains("a"));
+        assert!(listing(&dir.path().join("layers")).is_empty());
+
+        let mut writer = store.create("a").unwrap();
+        writer.write_all(b"layer").unwrap();
+        assert!(!store.contains("a"));
+        writer.commit().unwrap();
+
+        assert!(store.contains("a"));
+        assert_eq!(store.size("a"), Some(5));
+        assert!(store.modified("a").is_some());
+        let mut body = String::new();
+        store.open("a").unwrap().read_to_string(&mut body).unwrap();
+        assert_eq!(body, "layer");
+        assert_eq!(listing(&dir.path().join("layers")), ["a.zip"]);
+
+        store.remove("a").unwrap();
+        store.remove("a").unwrap();
+        assert!(!store.contains("a"));
+        assert_eq!(store.size("a"), None);
+    }
+
+    #[test]
+    fn put_file_moves_a_finished_zip_into_the_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalDirStore::new(dir.path().join("layers"));
+        let src = dir.path().join("staged.zip");
+        fs::write(&src, "zip").unwrap();
+
+        store.put_file("b", &src).unwrap();
+
+        assert!(!src.exists());
+        assert_eq!(fs::read_to_string(dir.path().join("layers/b.zip")).unwrap(), "zip");
+    }
+
+    #[test]
+    fn configured_storage_lives_outside_the_metadata() {
+        let repo = init_repo(&[]);
+        let nas = tempfile::tempdir().unwrap();
+        let mut config = crate::meta::load_config(repo.path()).unwrap();
+
+        assert_eq!(open_store(repo.path(), &config).unwrap().location(), repo.path().join(".blk/snapshots").to_string_lossy());
+
+        config.storage = Some(nas.path().join("blk"));
+        let store = open_store(repo.path(), &config).unwrap();
+        store.create("c").unwrap().commit().unwrap();
+        assert!(nas.path().join("blk/c.zip").is_file());
+        assert!(!repo.path().join(".blk/snapshots/c.zip").exists());
+
+        // Variáveis expandem; caminho relativo parte da raiz do repositório
+        config.storage = Some(PathBuf::from("${ROOT}/../elsewhere"));
+        let expected = repo.path().join("../elsewhere").to_string_lossy().to_string();
+        assert_eq!(open_store(repo.path(), &config).unwrap().location(), expected);
+        config.storage = Some(PathBuf::from("layers"));
+        let expected = repo.path().join("layers").to_string_lossy().to_string();
+        assert_eq!(open_store(repo.path(), &config).unwrap().location(), expected);
+        config.storage = Some(PathBuf::from("${NO_SUCH_BLK_VAR}"));
+        assert!(matches!(open_store(repo.path(), &config), Err(BlkError::Config(_))));
+    }
+}
@@ -0,0 +1,515 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::bundle::{previous_import, unique_set_id};
+use crate::error::{BlkError, BlkResult, Context, Report};
+use crate::events::{CancelToken, EngineEvent, EventSender};
+use crate::lock::RepoLock;
+use crate::meta::{
+    blk_dir, config_path, load_config, load_manifest, read_json, resolve_chain, save_config, save_manifest,
+    set_ids, sets_dir, write_json,
+};
+use crate::migrate::{format_version, stamp_format, upgrade_locked, FORMAT_VERSION};
+use crate::models::{BlkConfig, LayerInfo};
+use crate::storage::{open_store, SnapshotStore};
+
+// -----------------------------------------------------------------------------
+// Repositórios
+// -----------------------------------------------------------------------------
+
+/// Um lado da sincronização: metadados em `root/.blk`, camadas no seu store.
+struct Repo {
+    root: PathBuf,
+    store: Box<dyn SnapshotStore>,
+    index: HashMap<String, LayerInfo>,
+    index_dirty: bool,
+}
+
+fn index_path(root: &Path) -> PathBuf {
+    blk_dir(root).join("layers.json")
+}
+
+/// Aceita tanto a pasta que contém `.blk` quanto a própria `.blk`.
+fn remote_root(path: &Path) -> PathBuf {
+    if path.file_name().is_some_and(|n| n == ".blk") {
+        path.parent().unwrap_or(path).to_path_buf()
+    } else {
+        path.to_path_buf()
+    }
+}
+
+impl Repo {
+    fn open(root: &Path, config: &BlkConfig) -> BlkResult<Repo> {
+        let store = open_store(root, config)?;
+        // Índice ilegível só custa rehash
+        let index = read_json(&index_path(root)).ok().flatten().unwrap_or_default();
+        Ok(Repo {
+            root: root.to_path_buf(),
+            store,
+            index,
+            index_dirty: false,
+        })
+    }
+
+    /// Destino do push, já travado. Um destino vazio vira um repositório só de
+    /// sets; um de formato antigo é migrado como um repositório local. Antes
+    /// do lock só a própria `.blk` é criada, para caber o arquivo de lock.
+    fn open_push_target(path: &Path, tx: &EventSender) -> BlkResult<(Repo, RepoLock)> {
+        let root = remote_root(path);
+        let fresh = !blk_dir(&root).exists();
+        fs::create_dir_all(blk_dir(&root)).at("create directory", blk_dir(&root))?;
+        let lock = RepoLock::acquire(&root, "push (incoming)")?;
+
+        let sets = sets_dir(&root);
+        if !sets.is_dir() {
+            fs::create_dir_all(&sets).at("create directory", &sets)?;
+            if fresh {
+                stamp_format(&root)?;
+            }
+        }
+        if let Some(upgraded) = upgrade_locked(&root)? {
+            tx.send(EngineEvent::Warning(format!("Remote: {upgraded}"))).ok();
+        }
+        let config = load_config(&root)?;
+        if !config_path(&root).exists() {
+            save_config(&root, &config)?;
+        }
+
+        Ok((Repo::open(&root, &config)?, lock))
+    }
+
+    /// Origem do pull, já travada: um push ou delete de lá não troca camadas
+    /// no meio da leitura. Fora o lock nada é gravado, nem migração nem config;
+    /// um remoto de formato antigo é recusado, e abri-lo com o blk o atualiza.
+    fn open_pull_source(path: &Path) -> BlkResult<(Repo, RepoLock)> {
+        let root = remote_root(path);
+        if !sets_dir(&root).is_dir() {
+            return Err(BlkError::NotFound(format!(
+                "No BLK repository at {}",
+                root.to_string_lossy()
+            )));
+        }
+        let lock = RepoLock::acquire(&root, "pull (outgoing)")?;
+        let version = format_version(&root)?;
+        if version < FORMAT_VERSION {
+            return Err(BlkError::Integrity(format!(
+                "{} uses repository format v{version}; open it with blk once to upgrade it, then pull again",
+                root.to_string_lossy()
+            )));
+        }
+        let config = load_config(&root)?;
+        Ok((Repo::open(&root, &config)?, lock))
+    }
+
+    /// sha256 da camada, do cache quando tamanho e mtime ainda batem.
+    fn layer_hash(&mut self, id: &str) -> BlkResult<Option<String>> {
+        let (size, modified) = match (self.store.size(id), self.store.modified(id)) {
+            (Some(s), Some(m)) => (s, m),
+            _ => return Ok(None),
+        };
+        if let Some(info) = self.index.get(id) {
+            if info.size == size && info.modified == modified {
+                return Ok(Some(info.sha256.clone()));
+            }
+        }
+
+        let reader = self.store.open(id).at("open snapshot", id)?;
+        let sha256 = hash_stream(reader, &mut io::sink()).at("read snapshot", id)?;
+        self.remember(id, sha256.clone());
+        Ok(Some(sha256))
+    }
+
+    fn remember(&mut self, id: &str, sha256: String) {
+        if let (Some(size), Some(modified)) = (self.store.size(id), self.store.modified(id)) {
+            self.index.insert(
+                id.to_string(),
+                LayerInfo {
+                    size,
+                    modified,
+                    sha256,
+                },
+            );
+            self.index_dirty = true;
+        }
+    }
+
+    /// O índice é só cache: perder a gravação custa um rehash na próxima vez.
+    fn save_index(&self) -> BlkResult<()> {
+        if !self.index_dirty {
+            return Ok(());
+        }
+        write_json(&index_path(&self.root), &self.index)
+    }
+}
+
+fn hash_stream(mut src: impl Read, out: &mut impl Write) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1024 * 1024];
+    loop {
+        let n = src.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        out.write_all(&buf[..n])?;
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// sha256 de cada arquivo da camada, pelo nome no zip. Ler cada membro até o
+/// fim também faz o zip conferir o CRC32 dele.
+fn file_hashes(repo: &Repo, id: &str) -> BlkResult<BTreeMap<String, String>> {
+    let reader = repo.store.open(id).at("open snapshot", id)?;
+    let mut archive = zip::ZipArchive::new(reader).at("read snapshot", id)?;
+    let mut hashes = BTreeMap::new();
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).at("read snapshot", id)?;
+        let name = file.name().to_string();
+        let sha256 = hash_stream(&mut file, &mut io::sink()).at("read snapshot", id)?;
+        hashes.insert(name, sha256);
+    }
+    Ok(hashes)
+}
+
+// -----------------------------------------------------------------------------
+// Transferência
+// -----------------------------------------------------------------------------
+
+/// Copia uma camada e só aceita se o sha256 de cada arquivo dela bate nos dois
+/// lados. O hash do zip inteiro fica para o índice e para detectar uma camada
+/// trocada durante a cópia.
+fn copy_layer(src: &mut Repo, src_id: &str, dst: &mut Repo, dst_id: &str) -> BlkResult<()> {
+    let expected = src.layer_hash(src_id)?;
+    let files = file_hashes(src, src_id)?;
+
+    let reader = src.store.open(src_id).at("open snapshot", src_id)?;
+    let mut writer = dst.store.create(dst_id).at("create snapshot", dst_id)?;
+    let sent = hash_stream(reader, &mut writer).at("copy snapshot", src_id)?;
+    if expected.as_ref().is_some_and(|h| *h != sent) {
+        return Err(BlkError::Integrity(format!(
+            "Layer {src_id} changed while it was being copied"
+        )));
+    }
+    writer.commit().at("store snapshot", dst_id)?;
+
+    match file_hashes(dst, dst_id) {
+        Ok(received) if received == files => {}
+        _ => {
+            let _ = dst.store.remove(dst_id);
+            return Err(BlkError::Integrity(format!(
+                "Layer {dst_id} failed verification after copy"
+            )));
+        }
+    }
+
+    dst.remember(dst_id, sent);
+    Ok(())
+}
+
+struct SyncReport {
+    transferred: usize,
+    up_to_date: usize,
+    renamed: Vec<(String, String)>,
+    missing_scopes: HashSet<String>,
+}
+
+/// Leva `ids` (pais antes dos filhos) de `src` para `dst`. Manifesto só é
+/// gravado depois que a camada chegou e passou na conferência; um cancelamento
+/// só é atendido entre sets, então os já transferidos ficam completos.
+fn transfer(
+    src: &mut Repo,
+    dst: &mut Repo,
+    dst_config: Option<&BlkConfig>,
+    ids: &[String],
+    cancel: &CancelToken,
+    tx: &EventSender,
+) -> BlkResult<SyncReport> {
+    let mut report = SyncReport {
+        transferred: 0,
+        up_to_date: 0,
+        renamed: vec![],
+        missing_scopes: HashSet::new(),
+    };
+    let mut id_map: HashMap<String, String> = HashMap::new();
+    let mut taken: HashSet<String> = HashSet::new();
+    let total = ids.len().max(1) as f32;
+
+    for (i, id) in ids.iter().enumerate() {
+        cancel.check()?;
+        let man = match load_manifest(&src.root, id)? {
+            Some(m) => m,
+            None => continue,
+        };
+        tx.send(EngineEvent::phase(
+            5.0 + (i as f32 / total) * 90.0,
+            format!("Syncing {}/{} ({id})", i + 1, ids.len()),
+        ))
+        .ok();
+
+        let mut local = man.clone();
+        local.parent_id = man
+            .parent_id
+            .as_ref()
+            .map(|p| id_map.get(p).cloned().unwrap_or_else(|| p.clone()));
+        let src_hash = src.layer_hash(id)?;
+
+        if let Some(cfg) = dst_config {
+            for scope in &local.scopes {
+                if !cfg.path_map.contains_key(scope) {
+                    report.missing_scopes.insert(scope.clone());
+                }
+            }
+        }
+
+        // Mesmo set dos dois lados: só falta conferir (ou repor) a camada
+        let existing = load_manifest(&dst.root, id)?;
+        if existing.as_ref() == Some(&local) {
+            let dst_hash = dst.layer_hash(id)?;
+            if dst_hash == src_hash {
+                report.up_to_date += 1;
+                id_map.insert(id.clone(), id.clone());
+                continue;
+            }
+            if dst_hash.is_none() {
+                copy_layer(src, id, dst, id)?;
+                report.transferred += 1;
+                id_map.insert(id.clone(), id.clone());
+                continue;
+            }
+        }
+
+        if existing.is_some() {
+            // Conflito de ID: talvez já tenha chegado antes com outro nome
+            if let Some(prev) = previous_import(&dst.root, &local) {
+                if dst.layer_hash(&prev)? == src_hash {
+                    report.up_to_date += 1;
+                    id_map.insert(id.clone(), prev);
+                    continue;
+                }
+            }
+            local.id = unique_set_id(&dst.root, dst.store.as_ref(), id, &taken);
+            report.renamed.push((id.clone(), local.id.clone()));
+        }
+
+        if src_hash.is_some() {
+            copy_layer(src, id, dst, &local.id)?;
+        }
+        save_manifest(&dst.root, &local)?;
+        report.transferred += 1;
+        taken.insert(local.id.clone());
+        id_map.insert(id.clone(), local.id);
+    }
+
+    Ok(report)
+}
+
+/// Ordena pais antes dos filhos, sem repetir sets compartilhados.
+fn with_ancestors(root: &Path, ids: &[String]) -> BlkResult<Vec<String>> {
+    let mut ordered: Vec<String> = Vec::new();
+    for id in ids {
+        for man in resolve_chain(root, id)? {
+            if !ordered.contains(&man.id) {
+                ordered.push(man.id);
+            }
+        }
+    }
+    Ok(ordered)
+}
+
+fn finish_message(verb: &str, report: &SyncReport, tx: &EventSender) -> String {
+    let mut msg = format!(
+        "{verb} {} set(s), {} up to date",
+        report.transferred, report.up_to_date
+    );
+    for (from, to) in &report.renamed {
+        msg.push_str(&format!(" | {from} → {to}"));
+    }
+    if !report.missing_scopes.is_empty() {
+        let mut scopes: Vec<&String> = report.missing_scopes.iter().collect();
+        scopes.sort();
+        let names: Vec<&str> = scopes.iter().map(|s| s.as_str()).collect();
+        let warning = format!("Scopes not in path map: {}", names.join(", "));
+        tx.send(EngineEvent::Warning(warning)).ok();
+    }
+    msg
+}
+
+// -----------------------------------------------------------------------------
+// Push / Pull
+// -----------------------------------------------------------------------------
+
+/// Envia o set alvo e toda a sua linhagem para outro `.blk`.
+pub fn engine_push(
+    app_root: &Path,
+    config: BlkConfig,
+    target_id: String,
+    remote: PathBuf,
+    cancel: CancelToken,
+    tx: EventSender,
+) -> BlkResult<Report> {
+    tx.send(EngineEvent::Started { operation: "push" }).ok();
+    tx.send(EngineEvent::phase(0.0, format!("Pushing '{target_id}'..."))).ok();
+    let _lock = RepoLock::acquire(app_root, "push")?;
+
+    let mut local = Repo::open(app_root, &config)?;
+    let (mut dst, _remote_lock) = Repo::open_push_target(&remote, &tx)?;
+    let ids: Vec<String> = resolve_chain(app_root, &target_id)?
+        .into_iter()
+        .map(|m| m.id)
+        .collect();
+    let result = transfer(&mut local, &mut dst, None, &ids, &cancel, &tx);
+
+    // Hashes já calculados valem mesmo se a transferência parou no meio
+    let index_saved = [local.save_index(), dst.save_index()];
+    let mut report = Report::new(finish_message("Pushed", &result?, &tx));
+    for saved in index_saved {
+        report.check(saved);
+    }
+    Ok(report)
+}
+
+/// Traz todos os sets do outro `.blk` que faltam aqui.
+pub fn engine_pull(
+    app_root: &Path,
+    config: BlkConfig,
+    remote: PathBuf,
+    cancel: CancelToken,
+    tx: EventSender,
+) -> BlkResult<Report> {
+    tx.send(EngineEvent::Started { operation: "pull" }).ok();
+    tx.send(EngineEvent::phase(0.0, format!("Pulling from {}...", remote.to_string_lossy())))
+        .ok();
+    let _lock = RepoLock::acquire(app_root, "pull")?;
+
+    let mut local = Repo::open(app_root, &config)?;
+    let (mut src, _remote_lock) = Repo::open_pull_source(&remote)?;
+    let ids = with_ancestors(&src.root, &set_ids(&src.root))?;
+    let result = transfer(&mut src, &mut local, Some(&config), &ids, &cancel, &tx);
+
+    // O índice do remoto não é gravado: fora o lock, o pull não escreve lá
+    let index_saved = local.save_index();
+    let mut report = Report::new(finish_message("Pulled", &result?, &tx));
+    report.check(index_saved);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{init_repo, silent};
+
+    fn push(root: &Path, remote: &Path) -> BlkResult<Report> {
+        let config = load_config(root).unwrap();
+        engine_push(root, config, "vanilla".into(), remote.to_path_buf(), CancelToken::default(), silent())
+    }
+
+    fn pull(root: &Path, remote: &Path) -> BlkResult<Report> {
+        let config = load_config(root).unwrap();
+        engine_pull(root, config, remote.to_path_buf(), CancelToken::default(), silent())
+    }
+
+    fn listing(root: &Path) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = walkdir::WalkDir::new(root)
+            .into_iter()
+            .map(|e| e.unwrap().into_path())
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn second_push_finds_everything_up_to_date() {
+        let repo = init_repo(&[("a.txt", "a")]);
+        let remote = tempfile::tempdir().unwrap();
+
+        assert_eq!(push(repo.path(), remote.path()).unwrap().summary, "Pushed 1 set(s), 0 up to date");
+        assert_eq!(push(repo.path(), remote.path()).unwrap().summary, "Pushed 0 set(s), 1 up to date");
+        assert_eq!(set_ids(remote.path()), ["vanilla"]);
+    }
+
+    #[test]
+    fn push_refuses_a_layer_whose_files_do_not_check_out() {
+        let repo = init_repo(&[("a.txt", &"a".repeat(4096))]);
+        let remote = tempfile::tempdir().unwrap();
+        // Estraga os dados de a.txt sem mexer no tamanho do zip
+        let layer = blk_dir(repo.path()).join("snapshots/vanilla.zip");
+        let mut bytes = fs::read(&layer).unwrap();
+        let name = b"a.txt";
+        let at = bytes.windows(name.len()).position(|w| w == name).unwrap();
+        let extra = u16::from_le_bytes([bytes[at - 2], bytes[at - 1]]) as usize;
+        bytes[at + name.len() + extra + 1] ^= 0xff;
+        fs::write(&layer, bytes).unwrap();
+
+        assert!(push(repo.path(), remote.path()).is_err());
+        assert!(set_ids(remote.path()).is_empty());
+        assert!(!blk_dir(remote.path()).join("snapshots/vanilla.zip").exists());
+    }
+
+    #[test]
+    fn pulling_a_conflicting_set_twice_reuses_the_renamed_copy() {
+        let theirs = init_repo(&[("a.txt", "theirs")]);
+        let remote = tempfile::tempdir().unwrap();
+        push(theirs.path(), remote.path()).unwrap();
+        let mine = init_repo(&[("a.txt", "mine")]);
+
+        let first = pull(mine.path(), remote.path()).unwrap();
+        let second = pull(mine.path(), remote.path()).unwrap();
+
+        assert!(first.summary.starts_with("Pulled 1 set(s), 0 up to date | vanilla → "), "{}", first.summary);
+        assert_eq!(second.summary, "Pulled 0 set(s), 1 up to date");
+        assert_eq!(set_ids(mine.path()).len(), 2);
+    }
+
+    #[test]
+    fn push_onto_a_locked_remote_writes_nothing() {
+        let repo = init_repo(&[("a.txt", "a")]);
+        let remote = tempfile::tempdir().unwrap();
+        fs::create_dir(blk_dir(remote.path())).unwrap();
+        let held = RepoLock::acquire(remote.path(), "restore").unwrap();
+        let before = listing(remote.path());
+
+        let err = push(repo.path(), remote.path()).unwrap_err();
+
+        assert!(matches!(err, BlkError::Busy(_)), "{err}");
+        assert_eq!(listing(remote.path()), before);
+        drop(held);
+    }
+
+    #[test]
+    fn pull_waits_for_a_locked_remote() {
+        let theirs = init_repo(&[("a.txt", "theirs")]);
+        let remote = tempfile::tempdir().unwrap();
+        push(theirs.path(), remote.path()).unwrap();
+        let mine = init_repo(&[]);
+        let held = RepoLock::acquire(remote.path(), "push (incoming)").unwrap();
+
+        let err = pull(mine.path(), remote.path()).unwrap_err();
+
+        assert!(matches!(err, BlkError::Busy(_)), "{err}");
+        assert_eq!(set_ids(mine.path()), ["vanilla"]);
+        drop(held);
+        pull(mine.path(), remote.path()).unwrap();
+        assert!(!blk_dir(remote.path()).join("lock").exists());
+    }
+
+    #[test]
+    fn pull_refuses_an_old_remote_without_migrating_it() {
+        let theirs = init_repo(&[("a.txt", "theirs")]);
+        let remote = tempfile::tempdir().unwrap();
+        push(theirs.path(), remote.path()).unwrap();
+        // Sem format.json = repositório de antes do versionamento
+        fs::remove_file(blk_dir(remote.path()).join("format.json")).unwrap();
+        let before = listing(remote.path());
+        let mine = init_repo(&[]);
+
+        let err = pull(mine.path(), remote.path()).unwrap_err();
+
+        assert!(matches!(err, BlkError::Integrity(_)), "{err}");
+        assert_eq!(listing(remote.path()), before);
+        assert_eq!(set_ids(mine.path()), ["vanilla"]);
+    }
+}
@@ -0,0 +1,48 @@
+use std::fs;
+use std::path::Path;
+use std::sync::mpsc;
+
+use tempfile::TempDir;
+
+use crate::engine::{engine_auto_init, now_unix};
+use crate::events::EventSender;
+use crate::models::SetManifest;
+
+// -----------------------------------------------------------------------------
+// Apoio aos testes
+// -----------------------------------------------------------------------------
+
+/// Canal sem ninguém ouvindo: os eventos são descartados.
+pub(crate) fn silent() -> EventSender {
+    mpsc::channel().0
+}
+
+/// Cria `files` ("rel/path", conteúdo) dentro de `root`.
+pub(crate) fn write_files(root: &Path, files: &[(&str, &str)]) {
+    for (rel, body) in files {
+        let path = root.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, body).unwrap();
+    }
+}
+
+/// Repositório recém-iniciado (só o vanilla) com `files` no Root.
+pub(crate) fn init_repo(files: &[(&str, &str)]) -> TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    write_files(dir.path(), files);
+    engine_auto_init(dir.path(), silent()).unwrap();
+    dir
+}
+
+/// Manifesto mínimo de um set no scope Root.
+pub(crate) fn set_manifest(id: &str, parent: Option<&str>) -> SetManifest {
+    SetManifest {
+        id: id.into(),
+        name: id.into(),
+        parent_id: parent.map(str::to_string),
+        created_at: now_unix(),
+        scopes: vec!["Root".into()],
+        exclusions: vec![],
+        deleted_paths: vec![],
+    }
+}